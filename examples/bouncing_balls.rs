@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use bevy_2d_physics::{
+    collision_primitives::{CircleCollider, Primitives, AABB, OBB},
+    rigidbody::{ContinuousCollision, Rigidbody},
+    PhysicsPlugin, RigidbodyBundle,
+};
+use rand::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(PhysicsPlugin)
+        .add_startup_system(setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.spawn(Camera2dBundle::default());
+
+    commands.spawn(RigidbodyBundle {
+        transform: Transform::from_translation((-150.,28.,0.).into()),
+        global_transform: GlobalTransform::default(),
+        mesh: meshes.add(shape::Circle::new(20.).into()).into(),
+        material: materials.add(ColorMaterial::from(Color::PINK)),
+        visibility: Visibility::VISIBLE,
+        computed_visibility: ComputedVisibility::default(),
+        rigidbody: Rigidbody::new_shape_with_velocity(65., Primitives::Circle(CircleCollider::new(20.)), Vec2::new(200.,0.))
+    });
+    // commands.spawn(RigidbodyBundle {
+    //     transform: Transform::from_translation((120.,30.,0.).into()),
+    //     global_transform: GlobalTransform::default(),
+    //     mesh: meshes.add(shape::Circle::new(20.).into()).into(),
+    //     material: materials.add(ColorMaterial::from(Color::BLACK)),
+    //     visibility: Visibility::VISIBLE,
+    //     computed_visibility: ComputedVisibility::default(),
+    //     rigidbody: Rigidbody::new_shape_with_velocity(20., Primitives::Circle(CircleCollider::new(20.)), Vec2::new(0.,0.))
+    // });
+
+    let mut rng = thread_rng();
+    for _ in 0..20 {
+        let mag: f32 = rng.gen_range(100.0..1000.0);
+        let dir = Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+        let pos = Vec3::new(rng.gen_range(-100.0..100.0), rng.gen_range(-300.0..300.0), 0.);
+        // Fast enough to tunnel through the 10 unit thick walls without CCD
+        commands.spawn((
+            RigidbodyBundle {
+                transform: Transform::from_translation(pos),
+                global_transform: GlobalTransform::default(),
+                mesh: meshes.add(shape::Circle::new(20.).into()).into(),
+                material: materials.add(ColorMaterial::from(Color::BLACK)),
+                visibility: Visibility::VISIBLE,
+                computed_visibility: ComputedVisibility::default(),
+                rigidbody: Rigidbody::new_shape_with_velocity(30., Primitives::Circle(CircleCollider::new(20.)),mag * dir )
+            },
+            ContinuousCollision,
+        ));
+    }
+    let size_1 = Vec2::new(80.,90.);
+    let prim_1 = OBB::new(size_1.x, size_1.y);
+    commands.spawn(RigidbodyBundle {
+        transform: Transform::from_translation(Vec3::new(0.,0.,0.)),
+        global_transform: GlobalTransform::default(),
+        mesh: meshes.add(shape::Quad::new(size_1).into()).into(),
+        material: materials.add(ColorMaterial::from(Color::ORANGE)),
+        visibility: Visibility::VISIBLE,
+        computed_visibility: ComputedVisibility::default(),
+        rigidbody: Rigidbody::new_shape_with_velocity(30., Primitives::OBB(prim_1) , Vec2::new(50., 0.))
+    });
+
+    let size_2 = Vec2::new(50.,50.);
+    let prim_2 = AABB::new(size_2.x, size_2.y);
+    commands.spawn(RigidbodyBundle {
+        transform: Transform::from_translation(Vec3::new(25.,0.,0.)),
+        global_transform: GlobalTransform::default(),
+        mesh: meshes.add(shape::Quad::new(size_2).into()).into(),
+        material: materials.add(ColorMaterial::from(Color::PINK)),
+        visibility: Visibility::VISIBLE,
+        computed_visibility: ComputedVisibility::default(),
+        rigidbody: Rigidbody::new_static_shape(Primitives::AABB(prim_2))
+    });
+
+    let top = Vec2::new(500.,10.);
+    let prim_top = AABB::new(top.x, top.y);
+    commands.spawn(RigidbodyBundle {
+        transform: Transform::from_translation(Vec3::new(0.,405.,0.)),
+        global_transform: GlobalTransform::default(),
+        mesh: meshes.add(shape::Quad::new(top).into()).into(),
+        material: materials.add(ColorMaterial::from(Color::BLACK)),
+        visibility: Visibility::VISIBLE,
+        computed_visibility: ComputedVisibility::default(),
+        rigidbody: Rigidbody::new_static_shape(Primitives::AABB(prim_top))
+    });
+    let bottom = Vec2::new(500.,10.);
+    let prim_bottom = AABB::new(bottom.x, bottom.y);
+    commands.spawn(RigidbodyBundle {
+        transform: Transform::from_translation(Vec3::new(0.,-405.,0.)),
+        global_transform: GlobalTransform::default(),
+        mesh: meshes.add(shape::Quad::new(bottom).into()).into(),
+        material: materials.add(ColorMaterial::from(Color::BLACK)),
+        visibility: Visibility::VISIBLE,
+        computed_visibility: ComputedVisibility::default(),
+        rigidbody: Rigidbody::new_static_shape(Primitives::AABB(prim_bottom))
+    });
+    let left = Vec2::new(10.,800.);
+    let prim_left = AABB::new(left.x, left.y);
+    commands.spawn(RigidbodyBundle {
+        transform: Transform::from_translation(Vec3::new(-255.,0.,0.)),
+        global_transform: GlobalTransform::default(),
+        mesh: meshes.add(shape::Quad::new(left).into()).into(),
+        material: materials.add(ColorMaterial::from(Color::BLACK)),
+        visibility: Visibility::VISIBLE,
+        computed_visibility: ComputedVisibility::default(),
+        rigidbody: Rigidbody::new_static_shape(Primitives::AABB(prim_left))
+    });
+    let right = Vec2::new(10.,800.);
+    let prim_right = AABB::new(right.x, right.y);
+    commands.spawn(RigidbodyBundle {
+        transform: Transform::from_translation(Vec3::new(255.,0.,0.)),
+        global_transform: GlobalTransform::default(),
+        mesh: meshes.add(shape::Quad::new(right).into()).into(),
+        material: materials.add(ColorMaterial::from(Color::BLACK)),
+        visibility: Visibility::VISIBLE,
+        computed_visibility: ComputedVisibility::default(),
+        rigidbody: Rigidbody::new_static_shape(Primitives::AABB(prim_right))
+    });
+}
@@ -0,0 +1,322 @@
+use bevy::{prelude::*, utils::HashMap};
+
+/// Dynamic bounding volume hierarchy over axis aligned boxes, one leaf per entity.
+///
+/// Leaves store fattened bounds so a body can move a little without the tree changing,
+/// see [`AabbTree::update`]. Insertion follows the perimeter cost heuristic from Box2D.
+#[derive(Debug, Default)]
+pub struct AabbTree {
+    nodes: Vec<TreeNode>,
+    free_nodes: Vec<usize>,
+    root: Option<usize>,
+    leaves: HashMap<Entity, usize>,
+}
+
+#[derive(Debug, Clone)]
+struct TreeNode {
+    min: Vec2,
+    max: Vec2,
+    parent: Option<usize>,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NodeKind {
+    Leaf(Entity),
+    Branch([usize; 2]),
+    Free,
+}
+
+fn union(min_1: Vec2, max_1: Vec2, min_2: Vec2, max_2: Vec2) -> (Vec2, Vec2) {
+    (min_1.min(min_2), max_1.max(max_2))
+}
+
+fn perimeter(min: Vec2, max: Vec2) -> f32 {
+    let size = max - min;
+    2. * (size.x + size.y)
+}
+
+fn overlaps(min_1: Vec2, max_1: Vec2, min_2: Vec2, max_2: Vec2) -> bool {
+    max_1.x >= min_2.x && min_1.x <= max_2.x && max_1.y >= min_2.y && min_1.y <= max_2.y
+}
+
+impl AabbTree {
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.leaves.contains_key(&entity)
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.leaves.keys().copied()
+    }
+
+    /// Fattened bounds stored for `entity`.
+    pub fn get_bounds(&self, entity: Entity) -> Option<(Vec2, Vec2)> {
+        let node = &self.nodes[*self.leaves.get(&entity)?];
+        Some((node.min, node.max))
+    }
+
+    /// Inserts `entity` with its bounds grown by `margin` on every side, replacing any
+    /// previous leaf of the same entity.
+    pub fn insert(&mut self, entity: Entity, min: Vec2, max: Vec2, margin: f32) {
+        self.remove(entity);
+        let leaf = self.allocate(TreeNode {
+            min: min - margin,
+            max: max + margin,
+            parent: None,
+            kind: NodeKind::Leaf(entity),
+        });
+        self.leaves.insert(entity, leaf);
+        self.insert_leaf(leaf);
+    }
+
+    /// Reinserts `entity` only if its new bounds have left the fattened bounds stored in
+    /// the tree. Returns `true` if the tree changed.
+    pub fn update(&mut self, entity: Entity, min: Vec2, max: Vec2, margin: f32) -> bool {
+        if let Some((fat_min, fat_max)) = self.get_bounds(entity) {
+            if fat_min.cmple(min).all() && fat_max.cmpge(max).all() {
+                return false;
+            }
+        }
+        self.insert(entity, min, max, margin);
+        true
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(leaf) = self.leaves.remove(&entity) {
+            self.remove_leaf(leaf);
+            self.release(leaf);
+        }
+    }
+
+    /// Calls `f` with every entity whose fattened bounds overlap `min`..`max`.
+    pub fn query(&self, min: Vec2, max: Vec2, f: impl FnMut(Entity)) {
+        self.traverse(|node_min, node_max| overlaps(node_min, node_max, min, max), f);
+    }
+
+    /// Walks the tree, descending only into nodes for which `visit` returns `true` and
+    /// calling `f` for every leaf reached. Lets queries other than box overlap, such as
+    /// ray casts, prune the tree with their own bounds test.
+    pub fn traverse(&self, mut visit: impl FnMut(Vec2, Vec2) -> bool, mut f: impl FnMut(Entity)) {
+        let Some(root) = self.root else {
+            return;
+        };
+        let mut stack = vec![root];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !visit(node.min, node.max) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf(entity) => f(entity),
+                NodeKind::Branch(children) => stack.extend(children),
+                NodeKind::Free => {}
+            }
+        }
+    }
+
+    fn allocate(&mut self, node: TreeNode) -> usize {
+        if let Some(index) = self.free_nodes.pop() {
+            self.nodes[index] = node;
+            index
+        } else {
+            self.nodes.push(node);
+            self.nodes.len() - 1
+        }
+    }
+
+    fn release(&mut self, index: usize) {
+        self.nodes[index].kind = NodeKind::Free;
+        self.nodes[index].parent = None;
+        self.free_nodes.push(index);
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        let Some(root) = self.root else {
+            self.root = Some(leaf);
+            return;
+        };
+        let (leaf_min, leaf_max) = (self.nodes[leaf].min, self.nodes[leaf].max);
+
+        // Find the cheapest sibling by walking down the tree
+        let mut index = root;
+        while let NodeKind::Branch(children) = self.nodes[index].kind {
+            let node = &self.nodes[index];
+            let (combined_min, combined_max) = union(node.min, node.max, leaf_min, leaf_max);
+            let combined_perimeter = perimeter(combined_min, combined_max);
+
+            // Cost of making a new parent for this node and the leaf
+            let cost = 2. * combined_perimeter;
+            // Minimum cost of pushing the leaf further down the tree
+            let inheritance_cost = 2. * (combined_perimeter - perimeter(node.min, node.max));
+
+            let child_cost = |child: usize| {
+                let child = &self.nodes[child];
+                let (min, max) = union(child.min, child.max, leaf_min, leaf_max);
+                match child.kind {
+                    NodeKind::Leaf(_) => perimeter(min, max) + inheritance_cost,
+                    _ => perimeter(min, max) - perimeter(child.min, child.max) + inheritance_cost,
+                }
+            };
+            let cost_1 = child_cost(children[0]);
+            let cost_2 = child_cost(children[1]);
+
+            if cost < cost_1 && cost < cost_2 {
+                break;
+            }
+            index = if cost_1 < cost_2 { children[0] } else { children[1] };
+        }
+        let sibling = index;
+
+        // Create a new parent for the sibling and the leaf
+        let old_parent = self.nodes[sibling].parent;
+        let (min, max) = union(self.nodes[sibling].min, self.nodes[sibling].max, leaf_min, leaf_max);
+        let new_parent = self.allocate(TreeNode {
+            min,
+            max,
+            parent: old_parent,
+            kind: NodeKind::Branch([sibling, leaf]),
+        });
+        self.nodes[sibling].parent = Some(new_parent);
+        self.nodes[leaf].parent = Some(new_parent);
+        match old_parent {
+            Some(old_parent) => self.replace_child(old_parent, sibling, new_parent),
+            None => self.root = Some(new_parent),
+        }
+
+        self.refit(old_parent);
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        if self.root == Some(leaf) {
+            self.root = None;
+            return;
+        }
+        let Some(parent) = self.nodes[leaf].parent else {
+            return;
+        };
+        let NodeKind::Branch(children) = self.nodes[parent].kind else {
+            return;
+        };
+        let sibling = if children[0] == leaf { children[1] } else { children[0] };
+        let grand_parent = self.nodes[parent].parent;
+
+        // Collapse the parent, the sibling takes its place
+        self.nodes[sibling].parent = grand_parent;
+        match grand_parent {
+            Some(grand_parent) => self.replace_child(grand_parent, parent, sibling),
+            None => self.root = Some(sibling),
+        }
+        self.release(parent);
+        self.refit(grand_parent);
+    }
+
+    fn replace_child(&mut self, parent: usize, old_child: usize, new_child: usize) {
+        if let NodeKind::Branch(children) = &mut self.nodes[parent].kind {
+            for child in children.iter_mut() {
+                if *child == old_child {
+                    *child = new_child;
+                }
+            }
+        }
+    }
+
+    /// Recomputes the bounds of `index` and all its ancestors from their children.
+    fn refit(&mut self, mut index: Option<usize>) {
+        while let Some(i) = index {
+            if let NodeKind::Branch([child_1, child_2]) = self.nodes[i].kind {
+                let (min, max) = union(
+                    self.nodes[child_1].min,
+                    self.nodes[child_1].max,
+                    self.nodes[child_2].min,
+                    self.nodes[child_2].max,
+                );
+                self.nodes[i].min = min;
+                self.nodes[i].max = max;
+            }
+            index = self.nodes[i].parent;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queried(tree: &AabbTree, min: Vec2, max: Vec2) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        tree.query(min, max, |e| entities.push(e));
+        entities.sort();
+        entities
+    }
+
+    fn grid() -> (AabbTree, Vec<(Entity, Vec2, Vec2)>) {
+        let mut tree = AabbTree::default();
+        let mut boxes = Vec::new();
+        for i in 0..50 {
+            let entity = Entity::from_raw(i);
+            let min = Vec2::new((i % 10) as f32 * 15., (i / 10) as f32 * 15.);
+            let max = min + Vec2::splat(10.);
+            tree.insert(entity, min, max, 0.);
+            boxes.push((entity, min, max));
+        }
+        (tree, boxes)
+    }
+
+    #[test]
+    fn query_finds_every_overlapping_leaf() {
+        let (tree, boxes) = grid();
+        assert_eq!(tree.len(), 50);
+        for (min, max) in [(Vec2::new(12., 12.), Vec2::new(40., 33.)), (Vec2::splat(-5.), Vec2::splat(-1.)), (Vec2::splat(-1.), Vec2::splat(200.))] {
+            let mut expected: Vec<Entity> = boxes
+                .iter()
+                .filter(|(_, b_min, b_max)| overlaps(*b_min, *b_max, min, max))
+                .map(|(e, ..)| *e)
+                .collect();
+            expected.sort();
+            assert_eq!(queried(&tree, min, max), expected);
+        }
+    }
+
+    #[test]
+    fn removed_leaves_are_not_found() {
+        let (mut tree, boxes) = grid();
+        for (entity, ..) in boxes.iter().step_by(2) {
+            tree.remove(*entity);
+        }
+        assert_eq!(tree.len(), 25);
+        let expected: Vec<Entity> = boxes.iter().skip(1).step_by(2).map(|(e, ..)| *e).collect();
+        assert_eq!(queried(&tree, Vec2::splat(-1.), Vec2::splat(200.)), expected);
+        assert!(!tree.contains(boxes[0].0) && tree.contains(boxes[1].0));
+
+        for (entity, ..) in &boxes {
+            tree.remove(*entity);
+        }
+        assert!(tree.is_empty());
+        assert!(queried(&tree, Vec2::splat(-1.), Vec2::splat(200.)).is_empty());
+    }
+
+    #[test]
+    fn update_only_reinserts_leaves_that_leave_their_margin() {
+        let mut tree = AabbTree::default();
+        let entity = Entity::from_raw(0);
+        tree.insert(entity, Vec2::ZERO, Vec2::splat(10.), 5.);
+        assert_eq!(tree.get_bounds(entity), Some((Vec2::splat(-5.), Vec2::splat(15.))));
+
+        assert!(!tree.update(entity, Vec2::splat(3.), Vec2::splat(13.), 5.));
+        assert_eq!(tree.get_bounds(entity), Some((Vec2::splat(-5.), Vec2::splat(15.))));
+
+        assert!(tree.update(entity, Vec2::splat(50.), Vec2::splat(60.), 5.));
+        assert_eq!(tree.get_bounds(entity), Some((Vec2::splat(45.), Vec2::splat(65.))));
+        assert!(queried(&tree, Vec2::ZERO, Vec2::splat(10.)).is_empty());
+        assert_eq!(queried(&tree, Vec2::splat(55.), Vec2::splat(56.)), vec![entity]);
+        assert_eq!(tree.len(), 1);
+    }
+}
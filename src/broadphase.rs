@@ -0,0 +1,349 @@
+use bevy::{prelude::*, utils::{HashMap, HashSet}};
+
+use crate::aabb_tree::AabbTree;
+
+/// World space bounds of a single collider handed to a [`Broadphase`].
+#[derive(Debug, Clone, Copy)]
+pub struct BroadphaseProxy {
+    pub entity: Entity,
+    pub min: Vec2,
+    pub max: Vec2,
+    /// Pairs of two static proxies are never reported.
+    pub is_static: bool,
+}
+
+impl BroadphaseProxy {
+    pub fn overlaps(&self, other: &BroadphaseProxy) -> bool {
+        self.max.x >= other.min.x
+            && self.min.x <= other.max.x
+            && self.max.y >= other.min.y
+            && self.min.y <= other.max.y
+    }
+
+    fn can_collide(&self, other: &BroadphaseProxy) -> bool {
+        !(self.is_static && other.is_static) && self.overlaps(other)
+    }
+}
+
+/// Finds the pairs of colliders whose bounds overlap, so the narrowphase only has to
+/// run on those.
+pub trait Broadphase: Send + Sync {
+    /// Returns every pair `(i, j)` with `i < j` of indices into `proxies` whose bounds
+    /// overlap and that are not both static, sorted so the result is deterministic.
+    fn find_pairs(&mut self, proxies: &[BroadphaseProxy]) -> Vec<(usize, usize)>;
+
+    /// Moves the bodies of `proxies` to their bounds at the end of a step, after
+    /// [`Broadphase::find_pairs`] saw them at the start. Only needed by broadphases that
+    /// answer queries between steps.
+    fn update_bounds(&mut self, _proxies: &[BroadphaseProxy]) {}
+
+    /// Entities whose bounds, as of the end of the last step, may overlap `min`..`max`.
+    /// Returns `None` if the broadphase keeps no structure to answer this, in which case
+    /// callers have to test every collider.
+    fn query_bounds(&self, _min: Vec2, _max: Vec2) -> Option<Vec<Entity>> {
+        None
+    }
+
+    /// The tree backing this broadphase, if it has one. Lets spatial queries prune with
+    /// their own bounds tests.
+    fn trees(&self) -> Vec<&AabbTree> {
+        Vec::new()
+    }
+}
+
+/// The broadphase used by [`PhysicsPlugin`](crate::PhysicsPlugin).
+///
+/// Insert this resource before adding the plugin to swap the implementation.
+#[derive(Resource)]
+pub struct PhysicsBroadphase(pub Box<dyn Broadphase>);
+
+impl Default for PhysicsBroadphase {
+    fn default() -> Self {
+        Self(Box::<SweepAndPrune>::default())
+    }
+}
+
+/// Sorts proxies along the x axis and only tests proxies whose x intervals overlap.
+///
+/// Works well for any distribution of sizes, but degrades when many bodies share the
+/// same x range.
+#[derive(Debug, Default)]
+pub struct SweepAndPrune {
+    // Kept between steps, bodies rarely move far so the sort is nearly free
+    order: Vec<usize>,
+}
+
+impl Broadphase for SweepAndPrune {
+    fn find_pairs(&mut self, proxies: &[BroadphaseProxy]) -> Vec<(usize, usize)> {
+        if self.order.len() != proxies.len() {
+            self.order = (0..proxies.len()).collect();
+        }
+        self.order.sort_by(|a, b| proxies[*a].min.x.total_cmp(&proxies[*b].min.x));
+
+        let mut pairs = Vec::new();
+        let mut active: Vec<usize> = Vec::new();
+        for &i in &self.order {
+            let proxy = &proxies[i];
+            active.retain(|&j| proxies[j].max.x >= proxy.min.x);
+            for &j in &active {
+                if proxy.can_collide(&proxies[j]) {
+                    pairs.push((i.min(j), i.max(j)));
+                }
+            }
+            active.push(i);
+        }
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+/// Buckets proxies into square cells and only tests proxies sharing a cell.
+///
+/// Fastest when bodies are of similar size and `cell_size` is about the size of a
+/// typical body. Very large proxies cover many cells and should be avoided.
+#[derive(Debug)]
+pub struct UniformGrid {
+    pub cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl UniformGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+        }
+    }
+
+    fn cell(&self, point: Vec2) -> (i32, i32) {
+        let cell = (point / self.cell_size).floor();
+        (cell.x as i32, cell.y as i32)
+    }
+}
+
+impl Default for UniformGrid {
+    fn default() -> Self {
+        Self::new(64.)
+    }
+}
+
+impl Broadphase for UniformGrid {
+    fn find_pairs(&mut self, proxies: &[BroadphaseProxy]) -> Vec<(usize, usize)> {
+        // Reuse the buckets of cells that stay occupied between steps
+        for bucket in self.cells.values_mut() {
+            bucket.clear();
+        }
+        for (i, proxy) in proxies.iter().enumerate() {
+            let (min_x, min_y) = self.cell(proxy.min);
+            let (max_x, max_y) = self.cell(proxy.max);
+            for x in min_x..=max_x {
+                for y in min_y..=max_y {
+                    self.cells.entry((x, y)).or_default().push(i);
+                }
+            }
+        }
+        self.cells.retain(|_, bucket| !bucket.is_empty());
+
+        let mut pairs = HashSet::default();
+        for bucket in self.cells.values() {
+            for (n, &i) in bucket.iter().enumerate() {
+                for &j in &bucket[n + 1..] {
+                    if proxies[i].can_collide(&proxies[j]) {
+                        pairs.insert((i.min(j), i.max(j)));
+                    }
+                }
+            }
+        }
+        let mut pairs: Vec<_> = pairs.into_iter().collect();
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+/// Keeps dynamic and static bodies in two [`AabbTree`]s.
+///
+/// Dynamic leaves are fattened by `margin`, so a body is only reinserted once it has
+/// moved out of its fattened bounds. Static bodies live in their own tree which is only
+/// touched when a static body is added, removed or moved, and is never queried against
+/// itself. Suits large levels with a lot of static geometry.
+#[derive(Debug)]
+pub struct DynamicAabbTree {
+    pub margin: f32,
+    dynamic_tree: AabbTree,
+    static_tree: AabbTree,
+    // Index of each entity into the proxies of the current step
+    proxy_indices: HashMap<Entity, usize>,
+}
+
+impl DynamicAabbTree {
+    pub fn new(margin: f32) -> Self {
+        Self {
+            margin,
+            dynamic_tree: AabbTree::default(),
+            static_tree: AabbTree::default(),
+            proxy_indices: HashMap::default(),
+        }
+    }
+
+    /// Tree holding the dynamic bodies.
+    pub fn dynamic_tree(&self) -> &AabbTree {
+        &self.dynamic_tree
+    }
+
+    /// Tree holding the static bodies.
+    pub fn static_tree(&self) -> &AabbTree {
+        &self.static_tree
+    }
+}
+
+impl Default for DynamicAabbTree {
+    fn default() -> Self {
+        Self::new(5.)
+    }
+}
+
+impl Broadphase for DynamicAabbTree {
+    fn find_pairs(&mut self, proxies: &[BroadphaseProxy]) -> Vec<(usize, usize)> {
+        self.proxy_indices.clear();
+        for (i, proxy) in proxies.iter().enumerate() {
+            self.proxy_indices.insert(proxy.entity, i);
+            if proxy.is_static {
+                self.dynamic_tree.remove(proxy.entity);
+                // Static bodies do not move on their own, no need to fatten them
+                self.static_tree.update(proxy.entity, proxy.min, proxy.max, 0.);
+            } else {
+                self.static_tree.remove(proxy.entity);
+                self.dynamic_tree.update(proxy.entity, proxy.min, proxy.max, self.margin);
+            }
+        }
+
+        // Drop bodies that no longer exist
+        for tree in [&mut self.dynamic_tree, &mut self.static_tree] {
+            let stale: Vec<Entity> = tree
+                .entities()
+                .filter(|e| !self.proxy_indices.contains_key(e))
+                .collect();
+            for entity in stale {
+                tree.remove(entity);
+            }
+        }
+
+        let mut pairs = Vec::new();
+        for (i, proxy) in proxies.iter().enumerate().filter(|(_, p)| !p.is_static) {
+            let mut report = |entity: Entity| {
+                let j = self.proxy_indices[&entity];
+                if i != j && proxy.overlaps(&proxies[j]) {
+                    pairs.push((i.min(j), i.max(j)));
+                }
+            };
+            self.static_tree.query(proxy.min, proxy.max, &mut report);
+            self.dynamic_tree.query(proxy.min, proxy.max, &mut report);
+        }
+        // Every dynamic pair was found from both sides
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    fn update_bounds(&mut self, proxies: &[BroadphaseProxy]) {
+        for proxy in proxies {
+            if proxy.is_static {
+                self.static_tree.update(proxy.entity, proxy.min, proxy.max, 0.);
+            } else {
+                self.dynamic_tree.update(proxy.entity, proxy.min, proxy.max, self.margin);
+            }
+        }
+    }
+
+    fn query_bounds(&self, min: Vec2, max: Vec2) -> Option<Vec<Entity>> {
+        let mut entities = Vec::new();
+        self.static_tree.query(min, max, |e| entities.push(e));
+        self.dynamic_tree.query(min, max, |e| entities.push(e));
+        Some(entities)
+    }
+
+    fn trees(&self) -> Vec<&AabbTree> {
+        vec![&self.static_tree, &self.dynamic_tree]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn brute_force(proxies: &[BroadphaseProxy]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for i in 0..proxies.len() {
+            for j in i + 1..proxies.len() {
+                if proxies[i].can_collide(&proxies[j]) {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Boxes around the origin on whole coordinates, so some of them only touch at an edge.
+    fn random_proxies(rng: &mut StdRng, count: usize) -> Vec<BroadphaseProxy> {
+        (0..count)
+            .map(|i| {
+                let min = Vec2::new(rng.gen_range(-200..200) as f32, rng.gen_range(-200..200) as f32);
+                // A few large proxies spanning many grid cells
+                let max_size = if rng.gen_bool(0.05) { 150 } else { 30 };
+                let size = Vec2::new(rng.gen_range(0..max_size) as f32, rng.gen_range(0..max_size) as f32);
+                BroadphaseProxy {
+                    entity: Entity::from_raw(i as u32),
+                    min,
+                    max: min + size,
+                    is_static: rng.gen_bool(0.3),
+                }
+            })
+            .collect()
+    }
+
+    fn matches_brute_force(mut broadphase: impl Broadphase) {
+        let mut rng = StdRng::seed_from_u64(7);
+        // Reused across steps so state kept between them is exercised, including bodies
+        // being removed and switching between static and dynamic
+        for step in 0..20 {
+            let count = rng.gen_range(0..150);
+            let proxies = random_proxies(&mut rng, count);
+            let expected = brute_force(&proxies);
+            assert_eq!(broadphase.find_pairs(&proxies), expected, "step {step}");
+            broadphase.update_bounds(&proxies);
+        }
+    }
+
+    #[test]
+    fn sweep_and_prune_matches_brute_force() {
+        matches_brute_force(SweepAndPrune::default());
+    }
+
+    #[test]
+    fn uniform_grid_matches_brute_force() {
+        matches_brute_force(UniformGrid::new(16.));
+        matches_brute_force(UniformGrid::default());
+    }
+
+    #[test]
+    fn dynamic_aabb_tree_matches_brute_force() {
+        matches_brute_force(DynamicAabbTree::default());
+    }
+
+    #[test]
+    fn static_pairs_are_skipped() {
+        let proxy = |i: u32, is_static: bool| BroadphaseProxy {
+            entity: Entity::from_raw(i),
+            min: Vec2::new(-10., -10.),
+            max: Vec2::new(-5., -5.),
+            is_static,
+        };
+        let proxies = [proxy(0, true), proxy(1, true), proxy(2, false)];
+        let broadphases: [Box<dyn Broadphase>; 3] =
+            [Box::<SweepAndPrune>::default(), Box::<UniformGrid>::default(), Box::<DynamicAabbTree>::default()];
+        for mut broadphase in broadphases {
+            assert_eq!(broadphase.find_pairs(&proxies), vec![(0, 2), (1, 2)]);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use bevy::prelude::*;
+
+use crate::{
+    collision_primitives::{CollisionData, Primitives, Segment},
+    gjk::{self, ClosestPoints, SupportMap},
+    rigidbody::Rigidbody,
+    solver::SolverBody,
+};
+
+const MAX_ITERATIONS: usize = 20;
+/// Gap conservative advancement stops at, the regular contacts take over from there.
+const TARGET_DISTANCE: f32 = 0.1;
+
+pub(crate) type PartVisitor<'a> = dyn FnMut(&dyn SupportMap, &Transform, Option<Vec2>) + 'a;
+
+/// Calls `f` with every convex part of `shape`: the shape itself, the children of a
+/// compound or the segments of a polyline. Polyline segments come with the normal of
+/// their collision side since they are one sided.
+pub(crate) fn for_each_convex_part(shape: &Primitives, transform: &Transform, f: &mut PartVisitor) {
+    match shape {
+        Primitives::Compound(compound) => {
+            for child in compound.children() {
+                for_each_convex_part(&child.shape, &child.get_world_transform(transform), f);
+            }
+        }
+        Primitives::Polyline(polyline) => {
+            for edge in polyline.verteces().windows(2) {
+                let segment = Segment::new(edge[0], edge[1]);
+                let (a, b) = segment.get_world_endpoints(transform);
+                f(&segment, transform, Some((b - a).perp().normalize_or_zero()));
+            }
+        }
+        _ => {
+            if let Some(support_map) = shape.as_support_map() {
+                f(support_map, transform, None);
+            }
+        }
+    }
+}
+
+/// Time of impact of `shape_1` moving by `motion` against a resting `shape_2`, as a
+/// fraction of the motion, and the closest points of the shapes once they are less
+/// than `target_distance` apart.
+///
+/// Conservative advancement: the shapes are moved together by their distance divided
+/// by the speed they close in at, which can never overshoot for convex shapes that only
+/// translate. Returns `None` if they do not touch within the motion, already overlap or
+/// move apart.
+fn time_of_impact(
+    shape_1: &dyn SupportMap,
+    transform_1: &Transform,
+    motion: Vec2,
+    shape_2: &dyn SupportMap,
+    transform_2: &Transform,
+    target_distance: f32,
+) -> Option<(f32, ClosestPoints)> {
+    let mut toi = 0.;
+    for _ in 0..MAX_ITERATIONS {
+        let mut moved = *transform_1;
+        moved.translation += (motion * toi).extend(0.);
+        let closest = gjk::distance(shape_1, &moved, shape_2, transform_2)?;
+        // Separating shapes never hit, even when they start out close together
+        let closing_distance = -motion.dot(closest.normal);
+        if closing_distance <= 0. {
+            return None;
+        }
+        if closest.distance <= target_distance {
+            return Some((toi, closest));
+        }
+        toi += (closest.distance - target_distance / 2.) / closing_distance;
+        if toi > 1. {
+            return None;
+        }
+    }
+    None
+}
+
+/// Earliest time of impact over every pair of convex parts of the two shapes, see
+/// [`time_of_impact`].
+pub(crate) fn shape_time_of_impact(
+    shape_1: &Primitives,
+    transform_1: &Transform,
+    motion: Vec2,
+    shape_2: &Primitives,
+    transform_2: &Transform,
+    target_distance: f32,
+) -> Option<(f32, ClosestPoints)> {
+    let mut earliest: Option<(f32, ClosestPoints)> = None;
+    for_each_convex_part(shape_1, transform_1, &mut |part_1, part_transform_1, _| {
+        for_each_convex_part(shape_2, transform_2, &mut |part_2, part_transform_2, one_sided| {
+            let Some((toi, closest)) = time_of_impact(part_1, part_transform_1, motion, part_2, part_transform_2, target_distance) else {
+                return;
+            };
+            if matches!(one_sided, Some(side) if closest.normal.dot(side) <= 0.) {
+                return;
+            }
+            match earliest {
+                Some((earliest, _)) if earliest <= toi => {}
+                _ => earliest = Some((toi, closest)),
+            }
+        });
+    });
+    earliest
+}
+
+/// Contact between two shapes that do not touch yet but are less than `margin` apart,
+/// with the gap as a negative penetration depth. Uses the closest pair of convex parts.
+pub(crate) fn speculative_contact(
+    shape_1: &Primitives,
+    transform_1: &Transform,
+    shape_2: &Primitives,
+    transform_2: &Transform,
+    margin: f32,
+) -> Option<CollisionData> {
+    if margin <= 0. {
+        return None;
+    }
+    let mut closest: Option<ClosestPoints> = None;
+    for_each_convex_part(shape_1, transform_1, &mut |part_1, part_transform_1, one_sided_1| {
+        for_each_convex_part(shape_2, transform_2, &mut |part_2, part_transform_2, one_sided_2| {
+            let Some(points) = gjk::distance(part_1, part_transform_1, part_2, part_transform_2) else {
+                return;
+            };
+            if matches!(one_sided_1, Some(side) if points.normal.dot(side) >= 0.)
+                || matches!(one_sided_2, Some(side) if points.normal.dot(side) <= 0.)
+            {
+                return;
+            }
+            match closest {
+                Some(closest) if closest.distance <= points.distance => {}
+                _ => closest = Some(points),
+            }
+        });
+    });
+    let closest = closest.filter(|c| c.distance < margin)?;
+    Some(CollisionData {
+        unit_normal: closest.normal,
+        penetration_depth: -closest.distance,
+        contact_point: (closest.point_1 + closest.point_2) / 2.,
+    })
+}
+
+/// Stops bodies with [`ContinuousCollision`](crate::rigidbody::ContinuousCollision) at
+/// their first impact of the step instead of letting them pass through thin geometry.
+///
+/// Runs after positions are integrated. `start_positions` are the positions before that
+/// and `colliders` hold the transforms the step started from. A body that hits something is
+/// moved back to the time of impact and bounces off right away, the time left in the
+/// step is dropped. Bodies moving less than half their size are left to the regular
+/// contacts since they cannot tunnel. Sensors neither stop nor get stopped.
+pub(crate) fn solve_time_of_impacts(
+    bodies: &mut [SolverBody],
+    start_positions: &[Vec2],
+    colliders: &[(Entity, &Rigidbody, &Transform)],
+    continuous: &[bool],
+    sensors: &[bool],
+) {
+    for i in (0..bodies.len()).filter(|i| continuous[*i] && !sensors[*i]) {
+        let (_, rigidbody, transform) = colliders[i];
+        let motion = bodies[i].position - start_positions[i];
+        let (min, max) = rigidbody.shape.get_world_bounds(transform);
+        if motion.length() < (max - min).min_element() / 2. {
+            continue;
+        }
+        let (swept_min, swept_max) = (min.min(min + motion), max.max(max + motion));
+
+        let mut earliest: Option<(f32, Vec2, usize)> = None;
+        for j in (0..bodies.len()).filter(|j| *j != i && !sensors[*j]) {
+            let (_, other, other_transform) = colliders[j];
+            // Relative to the other body so it can be treated as resting
+            let other_motion = bodies[j].position - start_positions[j];
+            let (other_min, other_max) = other.shape.get_world_bounds(other_transform);
+            let (other_min, other_max) = (other_min.min(other_min + other_motion), other_max.max(other_max + other_motion));
+            if swept_max.cmplt(other_min).any() || swept_min.cmpgt(other_max).any() {
+                continue;
+            }
+            let relative_motion = motion - other_motion;
+            let Some((toi, closest)) = shape_time_of_impact(&rigidbody.shape, transform, relative_motion, &other.shape, other_transform, TARGET_DISTANCE) else {
+                continue;
+            };
+            match earliest {
+                Some((earliest, ..)) if earliest <= toi => {}
+                _ => earliest = Some((toi, closest.normal, j)),
+            }
+        }
+
+        let Some((toi, normal, j)) = earliest else {
+            continue;
+        };
+        bodies[i].position = start_positions[i] + motion * toi;
+
+        let relative_velocity = bodies[i].linear_velocity - bodies[j].linear_velocity;
+        let velocity_along_normal = relative_velocity.dot(normal);
+        let inverse_mass_sum = bodies[i].inverse_mass + bodies[j].inverse_mass;
+        if velocity_along_normal < 0. && inverse_mass_sum > 0. {
+            let restitution = rigidbody.restitution.resolve_restitutions(&colliders[j].1.restitution);
+            let impulse = normal * -(1. + restitution) * velocity_along_normal / inverse_mass_sum;
+            bodies[i].linear_velocity += impulse * bodies[i].inverse_mass;
+            bodies[j].linear_velocity -= impulse * bodies[j].inverse_mass;
+        }
+    }
+}
@@ -1,13 +1,239 @@
 use bevy::prelude::{*};
 
+use crate::gjk::{self, SupportMap};
+
+/// Result of a narrowphase test between two shapes.
+///
+/// `unit_normal` points from the second shape towards the first, i.e. the direction
+/// the first shape has to move to separate.
+#[derive(Debug, Clone, Copy)]
 pub struct CollisionData {
     pub unit_normal: Vec2,
+    /// Negative for speculative contacts, whose shapes are still that far apart.
+    pub penetration_depth: f32,
+    /// World space point the contact impulse acts through.
+    pub contact_point: Vec2,
+}
+
+impl CollisionData {
+    /// Swaps which shape is considered first, flipping the normal.
+    pub fn flipped(self) -> Self {
+        Self {
+            unit_normal: -self.unit_normal,
+            ..self
+        }
+    }
+}
+
+/// One point of a [`ContactManifold`].
+#[derive(Debug, Clone, Copy)]
+pub struct ManifoldPoint {
+    /// World space point the contact impulse acts through.
+    pub point: Vec2,
     pub penetration_depth: f32,
+    /// `point` in the local space of the first and the second body, filled in by
+    /// [`ContactManifold::set_local_anchors`].
+    pub local_anchor_1: Vec2,
+    pub local_anchor_2: Vec2,
+    /// Identifies the features of both shapes the point comes from. Stays the same
+    /// across steps while the same features touch, so impulses can be warm started.
+    pub feature: u32,
+}
+
+/// Every contact point between two shapes, at most two in 2D.
+///
+/// A box resting on the floor touches it along a whole edge, with a single point the
+/// solver can only push through the middle of that edge and the box rocks. Two points
+/// at the ends of the touching edges keep it flat and give the right torque when it
+/// tips. `unit_normal` follows the convention of [`CollisionData`].
+#[derive(Debug, Clone)]
+pub struct ContactManifold {
+    pub unit_normal: Vec2,
+    pub points: Vec<ManifoldPoint>,
+}
+
+impl ContactManifold {
+    /// Swaps which shape is considered first, flipping the normal and the anchors.
+    pub fn flipped(mut self) -> Self {
+        self.unit_normal = -self.unit_normal;
+        for point in &mut self.points {
+            std::mem::swap(&mut point.local_anchor_1, &mut point.local_anchor_2);
+        }
+        self
+    }
+
+    /// Stores every point relative to the transforms of the two bodies.
+    pub fn set_local_anchors(&mut self, transform_1: &Transform, transform_2: &Transform) {
+        let to_local = |transform: &Transform, point: Vec2| {
+            (transform.rotation.inverse() * (point.extend(0.) - transform.translation)).truncate()
+        };
+        for point in &mut self.points {
+            point.local_anchor_1 = to_local(transform_1, point.point);
+            point.local_anchor_2 = to_local(transform_2, point.point);
+        }
+    }
+
+    /// The whole manifold as the single point result of a narrowphase test, through the
+    /// middle of its points and as deep as the deepest of them.
+    pub fn merged(&self) -> CollisionData {
+        let count = self.points.len().max(1) as f32;
+        CollisionData {
+            unit_normal: self.unit_normal,
+            penetration_depth: self.points.iter().map(|p| p.penetration_depth).fold(f32::MIN, f32::max),
+            contact_point: self.points.iter().map(|p| p.point).sum::<Vec2>() / count,
+        }
+    }
+
+    /// Each point as the single point result of a narrowphase test.
+    pub fn collision_data(&self) -> impl Iterator<Item = (u32, CollisionData)> + '_ {
+        self.points.iter().map(|p| {
+            let collision_data = CollisionData {
+                unit_normal: self.unit_normal,
+                penetration_depth: p.penetration_depth,
+                contact_point: p.point,
+            };
+            (p.feature, collision_data)
+        })
+    }
+}
+
+impl From<CollisionData> for ContactManifold {
+    fn from(collision_data: CollisionData) -> Self {
+        Self {
+            unit_normal: collision_data.unit_normal,
+            points: vec![ManifoldPoint {
+                point: collision_data.contact_point,
+                penetration_depth: collision_data.penetration_depth,
+                local_anchor_1: Vec2::ZERO,
+                local_anchor_2: Vec2::ZERO,
+                feature: 0,
+            }],
+        }
+    }
+}
+
+/// Where a ray first enters a shape.
+///
+/// `toi` is in multiples of the ray's direction, so the hit point is `origin + dir * toi`.
+/// A ray starting inside a solid shape hits it at `toi` zero with a zero normal.
+#[derive(Debug, Clone, Copy)]
+pub struct RayIntersection {
+    pub toi: f32,
+    /// Surface normal at the hit point, facing the ray.
+    pub normal: Vec2,
 }
 
 pub enum Primitives {
     Circle(CircleCollider),
     AABB(AABB),
+    OBB(OBB),
+    ConvexPolygon(ConvexPolygon),
+    Capsule(Capsule),
+    Segment(Segment),
+    Polyline(Polyline),
+    Compound(Compound),
+}
+
+impl Primitives {
+    /// World space `(min, max)` of the axis aligned box enclosing the shape.
+    pub fn get_world_bounds(&self, transform: &Transform) -> (Vec2, Vec2) {
+        match self {
+            Primitives::Circle(c) => c.get_extents_on_world_axes(transform),
+            Primitives::AABB(c) => c.get_extents_as_global_vectors(transform),
+            Primitives::OBB(c) => c.get_extents_on_world_axes(transform),
+            Primitives::ConvexPolygon(c) => c.get_extents_on_world_axes(transform),
+            Primitives::Capsule(c) => c.get_extents_on_world_axes(transform),
+            Primitives::Segment(c) => c.get_extents_on_world_axes(transform),
+            Primitives::Polyline(c) => c.get_extents_on_world_axes(transform),
+            Primitives::Compound(c) => c.get_extents_on_world_axes(transform),
+        }
+    }
+
+    /// First hit of the ray `origin + dir * t` for `t` between zero and `max_toi`.
+    pub fn cast_ray(&self, transform: &Transform, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+        match self {
+            Primitives::Circle(c) => c.cast_ray(transform, origin, dir, max_toi),
+            Primitives::AABB(c) => c.cast_ray(transform, origin, dir, max_toi),
+            Primitives::OBB(c) => c.cast_ray(transform, origin, dir, max_toi),
+            Primitives::ConvexPolygon(c) => c.cast_ray(transform, origin, dir, max_toi),
+            Primitives::Capsule(c) => c.cast_ray(transform, origin, dir, max_toi),
+            Primitives::Segment(c) => c.cast_ray(transform, origin, dir, max_toi),
+            Primitives::Polyline(c) => c.cast_ray(transform, origin, dir, max_toi),
+            Primitives::Compound(c) => c.cast_ray(transform, origin, dir, max_toi),
+        }
+    }
+
+    /// Whether the world space `point` is inside the shape. Segments and polylines have
+    /// no inside.
+    pub fn contains_point(&self, transform: &Transform, point: Vec2) -> bool {
+        match self {
+            Primitives::Circle(c) => c.contains_point(transform, point),
+            Primitives::AABB(c) => c.contains_point(transform, point),
+            Primitives::OBB(c) => c.contains_point(transform, point),
+            Primitives::ConvexPolygon(c) => c.contains_point(transform, point),
+            Primitives::Capsule(c) => c.contains_point(transform, point),
+            Primitives::Segment(_) | Primitives::Polyline(_) => false,
+            Primitives::Compound(c) => c
+                .children()
+                .iter()
+                .any(|child| child.shape.contains_point(&child.get_world_transform(transform), point)),
+        }
+    }
+
+    /// World space vertices in counter clockwise order for shapes that are polygons,
+    /// together with the radius they are rounded by. A segment counts as a polygon with
+    /// two vertices and no area, a capsule as its segment rounded by its radius.
+    pub fn get_world_polygon(&self, transform: &Transform) -> Option<(Vec<Vec2>, f32)> {
+        match self {
+            Primitives::Segment(c) => {
+                let (a, b) = c.get_world_endpoints(transform);
+                Some((vec![a, b], 0.))
+            }
+            Primitives::Capsule(c) => {
+                let (a, b) = c.get_segment(transform);
+                Some((vec![a, b], c.radius))
+            }
+            Primitives::AABB(c) => {
+                let (obb, obb_transform) = c.as_obb(transform);
+                Some((obb.get_verteces(&obb_transform).to_vec(), 0.))
+            }
+            Primitives::OBB(c) => Some((c.get_verteces(transform).to_vec(), 0.)),
+            Primitives::ConvexPolygon(c) => Some((c.get_world_verteces(transform), 0.)),
+            _ => None,
+        }
+    }
+
+    /// The shape as seen by the generic narrowphase in [`gjk`](crate::gjk), `None` for
+    /// shapes that are not convex.
+    pub fn as_support_map(&self) -> Option<&dyn SupportMap> {
+        match self {
+            Primitives::Circle(c) => Some(c),
+            Primitives::AABB(c) => Some(c),
+            Primitives::OBB(c) => Some(c),
+            Primitives::ConvexPolygon(c) => Some(c),
+            Primitives::Capsule(c) => Some(c),
+            Primitives::Segment(c) => Some(c),
+            Primitives::Polyline(_) | Primitives::Compound(_) => None,
+        }
+    }
+
+    /// Moment of inertia around the shape's origin for `mass` spread evenly over its area.
+    /// Segments and polylines have no area and return zero.
+    pub fn moment_of_inertia(&self, mass: f32) -> f32 {
+        match self {
+            Primitives::Circle(c) => 0.5 * mass * c.radius * c.radius,
+            Primitives::AABB(c) => mass * (c.max - c.min).length_squared() / 12.,
+            Primitives::OBB(c) => mass * (c.half_extents * 2.).length_squared() / 12.,
+            Primitives::ConvexPolygon(c) => c.moment_of_inertia(mass),
+            Primitives::Capsule(c) => c.moment_of_inertia(mass),
+            Primitives::Segment(_) | Primitives::Polyline(_) => 0.,
+            Primitives::Compound(c) => {
+                // Keep the mass ratio between the children
+                let total = c.mass();
+                if total > 0. { c.moment_of_inertia() * mass / total } else { 0. }
+            }
+        }
+    }
 }
 
 pub trait CollisionPrimitive {
@@ -19,6 +245,8 @@ pub trait CollisionPrimitive {
     ) -> Option<CollisionData>;
 }
 
+/// Axis aligned box. It ignores the rotation of its transform, so a body using one
+/// never rotates, see [`Rigidbody::can_rotate`](crate::rigidbody::Rigidbody::can_rotate).
 pub struct AABB {
     min: Vec2,
     max: Vec2,
@@ -49,6 +277,43 @@ impl AABB {
         )
     }
 
+    pub fn cast_ray(&self, self_transform: &Transform, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+        let (min, max) = self.get_extents_as_global_vectors(self_transform);
+        cast_ray_bounds(min, max, origin, dir, max_toi)
+    }
+
+    pub fn contains_point(&self, self_transform: &Transform, point: Vec2) -> bool {
+        let (min, max) = self.get_extents_as_global_vectors(self_transform);
+        point.cmpge(min).all() && point.cmple(max).all()
+    }
+
+    /// Same box as an [`OBB`] at the transform's translation, ignoring its rotation.
+    pub fn as_obb(&self, self_transform: &Transform) -> (OBB, Transform) {
+        let center = (self.min + self.max) / 2.;
+        let transform = Transform::from_translation(self_transform.translation + center.extend(0.));
+        (OBB { half_extents: (self.max - self.min) / 2. }, transform)
+    }
+
+    pub fn is_colliding_with_obb(
+        &self,
+        self_transform: &Transform,
+        other: &OBB,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        let (obb, obb_transform) = self.as_obb(self_transform);
+        obb.is_colliding_with_obb(&obb_transform, other, other_transform)
+    }
+
+    pub fn is_colliding_with_polygon(
+        &self,
+        self_transform: &Transform,
+        other: &ConvexPolygon,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        let (obb, obb_transform) = self.as_obb(self_transform);
+        obb.is_colliding_with_polygon(&obb_transform, other, other_transform)
+    }
+
     pub fn is_colliding_with_aabb(
         &self,
         self_transform: &Transform,
@@ -100,9 +365,12 @@ impl AABB {
                     }
                     penetration = overlap_y;
                 }
+                // Contact is the centre of the overlapping region
+                let contact_point = (min_1.max(min_2) + max_1.min(max_2)) / 2.;
                 return Some(CollisionData {
                     unit_normal: normal,
                     penetration_depth: penetration,
+                    contact_point,
                 });
             }
         }
@@ -138,6 +406,13 @@ impl AABB {
         let axis_behind = edge_behind - closest;
         let axis_infront = edge_infront - closest;
         let reframed_center = center - closest;
+        // Rejecting the centre from the edge breaks down once the centre reaches the edge,
+        // use the edge's own normal facing away from the box instead
+        let box_center = (verts[0] + verts[2]) / 2.;
+        let outward_edge_normal = |axis: Vec2| {
+            let normal = axis.perp().normalize();
+            if normal.dot(closest - box_center) < 0. { -normal } else { normal }
+        };
 
         // SAT requires at least world alined axis overlap
         let (min_1, max_1) = self.get_extents_as_global_vectors(self_transform);
@@ -147,14 +422,26 @@ impl AABB {
         let normal;
         let pen_depth;
 
-        if reframed_center.dot(axis_behind) > 0. {
+        if center.cmpgt(min_1).all() && center.cmplt(max_1).all() {
+            // Centre inside the box, push out through the nearest face
+            let faces = [
+                (center.x - min_1.x, Vec2::NEG_X),
+                (max_1.x - center.x, Vec2::X),
+                (center.y - min_1.y, Vec2::NEG_Y),
+                (max_1.y - center.y, Vec2::Y),
+            ];
+            let (distance, face_normal) = faces.into_iter().min_by(|a, b| a.0.total_cmp(&b.0))?;
+            normal = face_normal;
+            pen_depth = distance + other.radius;
+        }
+        else if reframed_center.dot(axis_behind) > 0. {
             // Edge hit on behind edge
             // Voronoi region is edge
             pen_depth = is_overlaping_both_world_axes(min_1, max_1, min_2, max_2)?.min_element();
             // println!("Edge collision");
             // Collision!
             // Find the normal of the edge (cross product)
-            normal = reframed_center.reject_from(axis_behind).normalize();
+            normal = outward_edge_normal(axis_behind);
             // let normal = center.reject_from(c1 - c2).normalize();
         }
         else if reframed_center.dot(axis_infront) > 0. {
@@ -164,7 +451,7 @@ impl AABB {
             // println!("Edge collision");
             // Collision!
             // Find the normal of the edge (vector rejection)
-            normal = reframed_center.reject_from(axis_infront).normalize();
+            normal = outward_edge_normal(axis_infront);
         } 
         else {
             // Voronoi region is corner
@@ -191,11 +478,12 @@ impl AABB {
             // Collision!
             // println!("Corner collision");
         }
+        // normal points from the box to the circle, flip it so it points towards self
         Some(CollisionData {
-            unit_normal: normal,
+            unit_normal: -normal,
             penetration_depth: pen_depth,
+            contact_point: center - normal * (other.radius - pen_depth / 2.),
         })
-        
     }
 }
 
@@ -232,33 +520,474 @@ impl CircleCollider {
     }
 
     // Inline candidate
-    fn get_extents_on_world_axes(&self, self_transform: &Transform) -> (Vec2, Vec2) {
+    pub fn get_extents_on_world_axes(&self, self_transform: &Transform) -> (Vec2, Vec2) {
         (
             self_transform.translation.truncate() - self.radius,
             self_transform.translation.truncate() + self.radius,
         )
     }
 
+    pub fn cast_ray(&self, self_transform: &Transform, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+        cast_ray_circle(self_transform.translation.truncate(), self.radius, origin, dir, max_toi)
+    }
+
+    pub fn contains_point(&self, self_transform: &Transform, point: Vec2) -> bool {
+        self_transform.translation.truncate().distance_squared(point) <= self.radius * self.radius
+    }
+
     pub fn is_colliding_with_circle(
         &self,
         self_transform: &Transform,
         other: &CircleCollider,
         other_transform: &Transform,
     ) -> Option<CollisionData> {
-        let radius_squared = (self.radius + other.radius).powi(2);
-        let normal = (self_transform.translation - other_transform.translation).truncate();
-        if radius_squared > normal.length_squared() {
-            //Collision!
-            let distance = normal.length();
-            let penetration_depth = radius_squared.sqrt() - distance;
-            let unit_normal = normal / distance;
-            Some(CollisionData {
-                unit_normal,
-                penetration_depth,
-            })
+        // Concentric circles have no direction to push apart in, any will do
+        disc_contact(
+            self_transform.translation.truncate(),
+            self.radius,
+            other_transform.translation.truncate(),
+            other.radius,
+            Vec2::Y,
+        )
+    }
+
+    pub fn is_colliding_with_aabb(
+        &self,
+        self_transform: &Transform,
+        other: &AABB,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        other
+            .is_colliding_with_circle(other_transform, self, self_transform)
+            .map(CollisionData::flipped)
+    }
+
+    pub fn is_colliding_with_obb(
+        &self,
+        self_transform: &Transform,
+        other: &OBB,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        other
+            .is_colliding_with_circle(other_transform, self, self_transform)
+            .map(CollisionData::flipped)
+    }
+
+    pub fn is_colliding_with_polygon(
+        &self,
+        self_transform: &Transform,
+        other: &ConvexPolygon,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        other
+            .is_colliding_with_circle(other_transform, self, self_transform)
+            .map(CollisionData::flipped)
+    }
+
+    pub fn is_colliding_with_capsule(
+        &self,
+        self_transform: &Transform,
+        other: &Capsule,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        other
+            .is_colliding_with_circle(other_transform, self, self_transform)
+            .map(CollisionData::flipped)
+    }
+}
+
+/// Box that follows the rotation of its `Transform` about the Z axis.
+pub struct OBB {
+    pub half_extents: Vec2,
+}
+
+impl OBB {
+    pub fn new(length: f32, height: f32) -> Self {
+        Self {
+            half_extents: Vec2::new(length / 2., height / 2.),
+        }
+    }
+
+    /// World space directions of the local x and y axes.
+    pub fn get_axes(&self, self_transform: &Transform) -> [Vec2; 2] {
+        let x = (self_transform.rotation * Vec3::X).truncate().normalize();
+        [x, x.perp()]
+    }
+
+    pub fn get_verteces(&self, self_transform: &Transform) -> [Vec2; 4] {
+        let center = self_transform.translation.truncate();
+        let [x, y] = self.get_axes(self_transform);
+        let x = x * self.half_extents.x;
+        let y = y * self.half_extents.y;
+        // Counter clockwise
+        [
+            center + x + y,
+            center - x + y,
+            center - x - y,
+            center + x - y,
+        ]
+    }
+
+    /// `(min, max)` of the world aligned box enclosing the rotated box.
+    pub fn get_extents_on_world_axes(&self, self_transform: &Transform) -> (Vec2, Vec2) {
+        let center = self_transform.translation.truncate();
+        let [x, y] = self.get_axes(self_transform);
+        let extent = (x * self.half_extents.x).abs() + (y * self.half_extents.y).abs();
+        (center - extent, center + extent)
+    }
+
+    /// Whether the world space `point` is inside the box.
+    pub fn contains_point(&self, self_transform: &Transform, point: Vec2) -> bool {
+        let local = self.world_to_local(self_transform, point);
+        local.x.abs() <= self.half_extents.x && local.y.abs() <= self.half_extents.y
+    }
+
+    pub fn cast_ray(&self, self_transform: &Transform, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+        cast_ray_convex(&self.get_verteces(self_transform), origin, dir, max_toi)
+    }
+
+    fn world_to_local(&self, self_transform: &Transform, point: Vec2) -> Vec2 {
+        let [x, y] = self.get_axes(self_transform);
+        let offset = point - self_transform.translation.truncate();
+        Vec2::new(offset.dot(x), offset.dot(y))
+    }
+
+    fn local_to_world(&self, self_transform: &Transform, point: Vec2) -> Vec2 {
+        let [x, y] = self.get_axes(self_transform);
+        self_transform.translation.truncate() + x * point.x + y * point.y
+    }
+
+    /// SAT test over the two axes of each box. The clipped contact points are merged
+    /// into one, see [`clip_convex`] for all of them.
+    pub fn is_colliding_with_obb(
+        &self,
+        self_transform: &Transform,
+        other: &OBB,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        clip_convex(&self.get_verteces(self_transform), 0., &other.get_verteces(other_transform), 0.).map(|m| m.merged())
+    }
+
+    pub fn is_colliding_with_polygon(
+        &self,
+        self_transform: &Transform,
+        other: &ConvexPolygon,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        clip_convex(&self.get_verteces(self_transform), 0., &other.get_world_verteces(other_transform), 0.).map(|m| m.merged())
+    }
+
+    pub fn is_colliding_with_aabb(
+        &self,
+        self_transform: &Transform,
+        other: &AABB,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        let (other, other_transform) = other.as_obb(other_transform);
+        self.is_colliding_with_obb(self_transform, &other, &other_transform)
+    }
+
+    /// Finds the closest point of the box to the circle centre in box space.
+    pub fn is_colliding_with_circle(
+        &self,
+        self_transform: &Transform,
+        other: &CircleCollider,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        let center = self.world_to_local(self_transform, other_transform.translation.truncate());
+        let mut closest = center.clamp(-self.half_extents, self.half_extents);
+
+        let (local_normal, pen_depth) = if closest == center {
+            // Centre inside the box, push out through the nearest face
+            let face_distance = self.half_extents - center.abs();
+            if face_distance.x < face_distance.y {
+                closest.x = self.half_extents.x * center.x.signum();
+                (Vec2::new(center.x.signum(), 0.), face_distance.x + other.radius)
+            } else {
+                closest.y = self.half_extents.y * center.y.signum();
+                (Vec2::new(0., center.y.signum()), face_distance.y + other.radius)
+            }
         } else {
-            None
+            let offset = center - closest;
+            let distance = offset.length();
+            if distance >= other.radius {
+                return None;
+            }
+            (offset / distance, other.radius - distance)
+        };
+
+        // local_normal points from the box to the circle, flip it so it points towards self
+        let [x, y] = self.get_axes(self_transform);
+        let normal = x * local_normal.x + y * local_normal.y;
+        Some(CollisionData {
+            unit_normal: -normal,
+            penetration_depth: pen_depth,
+            // Halfway between the box surface and the deepest point of the circle
+            contact_point: self.local_to_world(self_transform, closest) - normal * pen_depth / 2.,
+        })
+    }
+}
+
+fn project_onto_axis(verts: &[Vec2], axis: Vec2) -> (f32, f32) {
+    verts.iter().fold((f32::MAX, f32::MIN), |(min, max), v| {
+        let p = v.dot(axis);
+        (min.min(p), max.max(p))
+    })
+}
+
+/// Outward unit normals of each edge `verts[i]` -> `verts[i + 1]` of a counter clockwise polygon.
+fn edge_normals(verts: &[Vec2]) -> impl Iterator<Item = Vec2> + '_ {
+    verts.iter().enumerate().map(|(i, a)| {
+        let b = verts[(i + 1) % verts.len()];
+        -(b - *a).perp().normalize()
+    })
+}
+
+/// Whether `point` lies inside the counter clockwise convex polygon `verts`.
+fn convex_contains(verts: &[Vec2], point: Vec2) -> bool {
+    verts
+        .iter()
+        .zip(edge_normals(verts))
+        .all(|(v, n)| (point - *v).dot(n) <= 0.)
+}
+
+/// Edge of the counter clockwise polygon `verts_1` with the largest separation from
+/// `verts_2`, and that separation. Negative when no edge separates them.
+fn max_separation(verts_1: &[Vec2], verts_2: &[Vec2]) -> (usize, f32) {
+    edge_normals(verts_1)
+        .enumerate()
+        .map(|(i, normal)| {
+            let deepest = verts_2
+                .iter()
+                .map(|v| (*v - verts_1[i]).dot(normal))
+                .fold(f32::MAX, f32::min);
+            (i, deepest)
+        })
+        .fold((0, f32::MIN), |best, edge| if edge.1 > best.1 { edge } else { best })
+}
+
+/// Contact manifold between two counter clockwise convex polygons rounded by `radius_1`
+/// and `radius_2`, adapted from Box2D's `b2CollidePolygons`.
+///
+/// The edge with the least penetration becomes the reference face. The edge of the
+/// other polygon facing it the most is clipped against the sides of the reference face
+/// and every clipped point below the face becomes a contact point. With a radius the
+/// face normal is only right when the shapes touch along it and not at a rounded corner,
+/// which is up to the caller to check.
+pub fn clip_convex(verts_1: &[Vec2], radius_1: f32, verts_2: &[Vec2], radius_2: f32) -> Option<ContactManifold> {
+    let radius = radius_1 + radius_2;
+    let (edge_1, separation_1) = max_separation(verts_1, verts_2);
+    if separation_1 > radius {
+        return None;
+    }
+    let (edge_2, separation_2) = max_separation(verts_2, verts_1);
+    if separation_2 > radius {
+        return None;
+    }
+
+    // Prefer the first polygon so the reference face does not flip between steps
+    // when both separations are nearly the same
+    let flip = separation_2 > separation_1 + 1e-3;
+    let (reference, incident, reference_edge, reference_radius, incident_radius) = if flip {
+        (verts_2, verts_1, edge_2, radius_2, radius_1)
+    } else {
+        (verts_1, verts_2, edge_1, radius_1, radius_2)
+    };
+
+    let v_1 = reference[reference_edge];
+    let v_2 = reference[(reference_edge + 1) % reference.len()];
+    let tangent = (v_2 - v_1).normalize();
+    let normal = -tangent.perp();
+
+    // Incident edge, the one whose normal is the most anti parallel to the reference face
+    let incident_edge = edge_normals(incident)
+        .enumerate()
+        .min_by(|a, b| a.1.dot(normal).total_cmp(&b.1.dot(normal)))?
+        .0;
+    let incident_next = (incident_edge + 1) % incident.len();
+    // Feature of each end of the clipped segment, the incident vertex or a side plane
+    let mut clipped = vec![
+        (incident[incident_edge], incident_edge as u32),
+        (incident[incident_next], incident_next as u32),
+    ];
+
+    // Clip against both side planes of the reference face
+    for (side, side_normal, offset) in [(0, -tangent, -tangent.dot(v_1)), (1, tangent, tangent.dot(v_2))] {
+        let distances: Vec<f32> = clipped.iter().map(|(p, _)| side_normal.dot(*p) - offset).collect();
+        let mut kept: Vec<(Vec2, u32)> = clipped
+            .iter()
+            .zip(&distances)
+            .filter(|(_, d)| **d <= 0.)
+            .map(|(p, _)| *p)
+            .collect();
+        if distances[0] * distances[1] < 0. {
+            let t = distances[0] / (distances[0] - distances[1]);
+            kept.push((clipped[0].0.lerp(clipped[1].0, t), 0x80 | side));
+        }
+        if kept.len() < 2 {
+            return None;
+        }
+        clipped = kept;
+    }
+
+    let points: Vec<ManifoldPoint> = clipped
+        .into_iter()
+        .filter_map(|(point, incident_feature)| {
+            let separation = (point - v_1).dot(normal);
+            // Halfway between the surfaces of both polygons
+            let on_reference = point - normal * (separation - reference_radius);
+            let on_incident = point - normal * incident_radius;
+            (separation <= radius).then(|| ManifoldPoint {
+                point: (on_reference + on_incident) / 2.,
+                penetration_depth: radius - separation,
+                local_anchor_1: Vec2::ZERO,
+                local_anchor_2: Vec2::ZERO,
+                feature: reference_edge as u32 | incident_feature << 8 | (flip as u32) << 16,
+            })
+        })
+        .collect();
+    if points.is_empty() {
+        return None;
+    }
+
+    // The reference normal points away from the reference polygon
+    let unit_normal = if flip { normal } else { -normal };
+    Some(ContactManifold { unit_normal, points })
+}
+
+/// Why a vertex list was rejected by [`ConvexPolygon::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonError {
+    /// Fewer than three vertices.
+    TooFewVertices,
+    /// Zero area or two consecutive vertices on top of each other.
+    Degenerate,
+    /// The outline bends inwards or crosses itself.
+    NotConvex,
+}
+
+impl std::fmt::Display for PolygonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolygonError::TooFewVertices => write!(f, "a polygon needs at least three vertices"),
+            PolygonError::Degenerate => write!(f, "the polygon has no area or repeats a vertex"),
+            PolygonError::NotConvex => write!(f, "the polygon is not convex"),
+        }
+    }
+}
+
+impl std::error::Error for PolygonError {}
+
+/// Convex polygon with vertices relative to the centre of mass of its body.
+pub struct ConvexPolygon {
+    // Counter clockwise around the centroid, validated by `new`
+    verteces: Vec<Vec2>,
+}
+
+impl ConvexPolygon {
+    /// Builds a polygon from vertices in either winding order. Clockwise input is reversed
+    /// so the polygon is always stored counter clockwise.
+    ///
+    /// The vertices are shifted so the centroid of the polygon lies on the origin, which
+    /// the solver treats as the centre of mass of the body.
+    pub fn new(mut verteces: Vec<Vec2>) -> Result<Self, PolygonError> {
+        if verteces.len() < 3 {
+            return Err(PolygonError::TooFewVertices);
+        }
+        let count = verteces.len();
+        let edges = || (0..count).map(|i| (i, (i + 1) % count));
+        if edges().any(|(i, j)| verteces[i].distance_squared(verteces[j]) <= f32::EPSILON) {
+            return Err(PolygonError::Degenerate);
+        }
+
+        // Shoelace formula, positive for counter clockwise
+        let double_area: f32 = edges().map(|(i, j)| verteces[i].perp_dot(verteces[j])).sum();
+        if double_area.abs() <= f32::EPSILON {
+            return Err(PolygonError::Degenerate);
+        }
+        if double_area < 0. {
+            verteces.reverse();
+        }
+
+        // Every vertex has to lie left of every edge. Checking only the corners would let
+        // outlines that wind around more than once through, such as a pentagram
+        for (i, j) in edges() {
+            let (a, b) = (verteces[i], verteces[j]);
+            if verteces.iter().any(|v| (b - a).perp_dot(*v - a) < -f32::EPSILON * double_area.abs()) {
+                return Err(PolygonError::NotConvex);
+            }
+        }
+
+        let centroid = edges()
+            .map(|(i, j)| (verteces[i] + verteces[j]) * verteces[i].perp_dot(verteces[j]))
+            .sum::<Vec2>()
+            / (3. * double_area.abs());
+        for vertex in &mut verteces {
+            *vertex -= centroid;
+        }
+        Ok(Self { verteces })
+    }
+
+    /// Local vertices in counter clockwise order.
+    pub fn verteces(&self) -> &[Vec2] {
+        &self.verteces
+    }
+
+    pub fn get_world_verteces(&self, self_transform: &Transform) -> Vec<Vec2> {
+        self.verteces
+            .iter()
+            .map(|v| self_transform.transform_point(v.extend(0.)).truncate())
+            .collect()
+    }
+
+    pub fn get_extents_on_world_axes(&self, self_transform: &Transform) -> (Vec2, Vec2) {
+        self.get_world_verteces(self_transform)
+            .into_iter()
+            .fold((Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)), |(min, max), v| {
+                (min.min(v), max.max(v))
+            })
+    }
+
+    pub fn contains_point(&self, self_transform: &Transform, point: Vec2) -> bool {
+        convex_contains(&self.get_world_verteces(self_transform), point)
+    }
+
+    pub fn cast_ray(&self, self_transform: &Transform, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+        cast_ray_convex(&self.get_world_verteces(self_transform), origin, dir, max_toi)
+    }
+
+    /// Moment of inertia around the centroid for `mass` spread evenly over the polygon.
+    pub fn moment_of_inertia(&self, mass: f32) -> f32 {
+        let count = self.verteces.len();
+        let (mut numerator, mut denominator) = (0., 0.);
+        for i in 0..count {
+            let a = self.verteces[i];
+            let b = self.verteces[(i + 1) % count];
+            let cross = a.perp_dot(b);
+            numerator += cross * (a.dot(a) + a.dot(b) + b.dot(b));
+            denominator += cross;
         }
+        mass * numerator / (6. * denominator)
+    }
+
+    /// SAT test over the edge normals of both polygons. The clipped contact points are
+    /// merged into one, see [`clip_convex`] for all of them.
+    pub fn is_colliding_with_polygon(
+        &self,
+        self_transform: &Transform,
+        other: &ConvexPolygon,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        clip_convex(&self.get_world_verteces(self_transform), 0., &other.get_world_verteces(other_transform), 0.).map(|m| m.merged())
+    }
+
+    pub fn is_colliding_with_obb(
+        &self,
+        self_transform: &Transform,
+        other: &OBB,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        clip_convex(&self.get_world_verteces(self_transform), 0., &other.get_verteces(other_transform), 0.).map(|m| m.merged())
     }
 
     pub fn is_colliding_with_aabb(
@@ -267,6 +996,996 @@ impl CircleCollider {
         other: &AABB,
         other_transform: &Transform,
     ) -> Option<CollisionData> {
-        other.is_colliding_with_circle(other_transform, self, self_transform)
+        let (other, other_transform) = other.as_obb(other_transform);
+        self.is_colliding_with_obb(self_transform, &other, &other_transform)
+    }
+
+    /// Finds the closest point on the outline to the circle centre. If the centre is
+    /// inside, pushes out through the edge it is closest to.
+    pub fn is_colliding_with_circle(
+        &self,
+        self_transform: &Transform,
+        other: &CircleCollider,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        let verts = self.get_world_verteces(self_transform);
+        let center = other_transform.translation.truncate();
+
+        let (normal, pen_depth) = if convex_contains(&verts, center) {
+            // Edge with the least negative separation is the closest one
+            let (separation, normal) = verts
+                .iter()
+                .zip(edge_normals(&verts))
+                .map(|(v, n)| ((center - *v).dot(n), n))
+                .max_by(|a, b| a.0.total_cmp(&b.0))?;
+            (normal, other.radius - separation)
+        } else {
+            let closest = verts
+                .iter()
+                .enumerate()
+                .map(|(i, a)| closest_point_on_segment(*a, verts[(i + 1) % verts.len()], center))
+                .min_by(|a, b| a.distance_squared(center).total_cmp(&b.distance_squared(center)))?;
+            let offset = center - closest;
+            let distance = offset.length();
+            if distance >= other.radius {
+                return None;
+            }
+            (offset / distance, other.radius - distance)
+        };
+
+        // normal points from the polygon to the circle, flip it so it points towards self
+        Some(CollisionData {
+            unit_normal: -normal,
+            penetration_depth: pen_depth,
+            contact_point: center - normal * (other.radius - pen_depth / 2.),
+        })
+    }
+}
+
+/// Point on the segment `a`..`b` closest to `point`.
+fn closest_point_on_segment(a: Vec2, b: Vec2, point: Vec2) -> Vec2 {
+    let ab = b - a;
+    if ab.length_squared() <= f32::EPSILON {
+        return a;
+    }
+    let t = ((point - a).dot(ab) / ab.length_squared()).clamp(0., 1.);
+    a + ab * t
+}
+
+/// Closest points `(on a, on b)` between the segments `a_1`..`a_2` and `b_1`..`b_2`.
+fn closest_points_between_segments(a_1: Vec2, a_2: Vec2, b_1: Vec2, b_2: Vec2) -> (Vec2, Vec2) {
+    // Adapted from Real-Time Collision Detection, Ericson, 5.1.9
+    let d_1 = a_2 - a_1;
+    let d_2 = b_2 - b_1;
+    let r = a_1 - b_1;
+    let a = d_1.length_squared();
+    let e = d_2.length_squared();
+    let f = d_2.dot(r);
+
+    if a <= f32::EPSILON && e <= f32::EPSILON {
+        return (a_1, b_1);
+    }
+    let (s, t) = if a <= f32::EPSILON {
+        (0., (f / e).clamp(0., 1.))
+    } else {
+        let c = d_1.dot(r);
+        if e <= f32::EPSILON {
+            ((-c / a).clamp(0., 1.), 0.)
+        } else {
+            let b = d_1.dot(d_2);
+            let denom = a * e - b * b;
+            let s = if denom > f32::EPSILON {
+                ((b * f - c * e) / denom).clamp(0., 1.)
+            } else {
+                // Parallel, any point will do
+                0.
+            };
+            let t = (b * s + f) / e;
+            if t < 0. {
+                ((-c / a).clamp(0., 1.), 0.)
+            } else if t > 1. {
+                (((b - c) / a).clamp(0., 1.), 1.)
+            } else {
+                (s, t)
+            }
+        }
+    };
+    (a_1 + d_1 * s, b_1 + d_2 * t)
+}
+
+/// Whether the segments `a_1`..`a_2` and `b_1`..`b_2` cross.
+fn segments_intersect(a_1: Vec2, a_2: Vec2, b_1: Vec2, b_2: Vec2) -> bool {
+    let d_1 = a_2 - a_1;
+    let d_2 = b_2 - b_1;
+    let side = |d: Vec2, origin: Vec2, p: Vec2| d.perp_dot(p - origin);
+    side(d_1, a_1, b_1) * side(d_1, a_1, b_2) <= 0. && side(d_2, b_1, a_1) * side(d_2, b_1, a_2) <= 0.
+}
+
+/// Contact between two discs, the normal points from the second to the first.
+/// `fallback_normal` is used when the centres coincide.
+fn disc_contact(center_1: Vec2, radius_1: f32, center_2: Vec2, radius_2: f32, fallback_normal: Vec2) -> Option<CollisionData> {
+    let offset = center_1 - center_2;
+    let radius = radius_1 + radius_2;
+    if offset.length_squared() >= radius * radius {
+        return None;
+    }
+    let distance = offset.length();
+    let unit_normal = if distance > f32::EPSILON { offset / distance } else { fallback_normal };
+    let penetration_depth = radius - distance;
+    Some(CollisionData {
+        unit_normal,
+        penetration_depth,
+        contact_point: center_1 - unit_normal * (radius_1 - penetration_depth / 2.),
+    })
+}
+
+/// Segment with a radius, the segment runs along the local y axis. The usual shape for
+/// character bodies since it slides over steps and seams without catching.
+pub struct Capsule {
+    /// Half the distance between the centres of the two caps.
+    pub half_length: f32,
+    pub radius: f32,
+}
+
+impl Capsule {
+    /// `length` is the distance between the centres of the two caps, the total height
+    /// of the capsule is `length + 2 * radius`.
+    pub fn new(length: f32, radius: f32) -> Self {
+        Self {
+            half_length: length / 2.,
+            radius,
+        }
+    }
+
+    /// World space end points of the inner segment.
+    pub fn get_segment(&self, self_transform: &Transform) -> (Vec2, Vec2) {
+        let axis = (self_transform.rotation * Vec3::Y).truncate() * self.half_length;
+        let center = self_transform.translation.truncate();
+        (center - axis, center + axis)
+    }
+
+    pub fn get_extents_on_world_axes(&self, self_transform: &Transform) -> (Vec2, Vec2) {
+        let (a, b) = self.get_segment(self_transform);
+        (a.min(b) - self.radius, a.max(b) + self.radius)
+    }
+
+    pub fn contains_point(&self, self_transform: &Transform, point: Vec2) -> bool {
+        let (a, b) = self.get_segment(self_transform);
+        closest_point_on_segment(a, b, point).distance_squared(point) <= self.radius * self.radius
+    }
+
+    pub fn cast_ray(&self, self_transform: &Transform, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+        if self.contains_point(self_transform, origin) {
+            return Some(RayIntersection { toi: 0., normal: Vec2::ZERO });
+        }
+        // Both caps and both straight sides, whichever is hit first
+        let (a, b) = self.get_segment(self_transform);
+        let side = (b - a).perp().normalize_or_zero() * self.radius;
+        [
+            cast_ray_circle(a, self.radius, origin, dir, max_toi),
+            cast_ray_circle(b, self.radius, origin, dir, max_toi),
+            cast_ray_segment(a + side, b + side, origin, dir, max_toi),
+            cast_ray_segment(a - side, b - side, origin, dir, max_toi),
+        ]
+        .into_iter()
+        .flatten()
+        .min_by(|h_1, h_2| h_1.toi.total_cmp(&h_2.toi))
+    }
+
+    /// Moment of inertia around the centre for `mass` spread evenly over the capsule.
+    pub fn moment_of_inertia(&self, mass: f32) -> f32 {
+        let (radius, half_length) = (self.radius, self.half_length);
+        let box_area = 4. * radius * half_length;
+        let circle_area = std::f32::consts::PI * radius * radius;
+        let density = mass / (box_area + circle_area);
+        let box_mass = density * box_area;
+        let circle_mass = density * circle_area;
+        let box_moment = box_mass * (4. * radius * radius + 4. * half_length * half_length) / 12.;
+        // Each cap is half a disc whose centroid sits 4r/3π beyond the end of the segment
+        let cap_offset = 4. * radius / (3. * std::f32::consts::PI);
+        let circle_moment = circle_mass * (0.5 * radius * radius + half_length * half_length + 2. * half_length * cap_offset);
+        box_moment + circle_moment
+    }
+
+    /// Separating direction used when the closest points of two shapes coincide.
+    fn fallback_normal(&self, self_transform: &Transform) -> Vec2 {
+        (self_transform.rotation * Vec3::X).truncate()
+    }
+
+    pub fn is_colliding_with_circle(
+        &self,
+        self_transform: &Transform,
+        other: &CircleCollider,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        let (a, b) = self.get_segment(self_transform);
+        let center = other_transform.translation.truncate();
+        let closest = closest_point_on_segment(a, b, center);
+        disc_contact(closest, self.radius, center, other.radius, self.fallback_normal(self_transform))
+    }
+
+    pub fn is_colliding_with_capsule(
+        &self,
+        self_transform: &Transform,
+        other: &Capsule,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        let (a_1, a_2) = self.get_segment(self_transform);
+        let (b_1, b_2) = other.get_segment(other_transform);
+        swept_segments_contact((a_1, a_2, self.radius), (b_1, b_2, other.radius), self.fallback_normal(self_transform))
+    }
+}
+
+fn project_capsule(a: Vec2, b: Vec2, radius: f32, axis: Vec2) -> (f32, f32) {
+    let (min, max) = project_onto_axis(&[a, b], axis);
+    (min - radius, max + radius)
+}
+
+/// Contact between two segments grown by a radius, each given as `(start, end, radius)`.
+/// Covers capsules and, with a radius of zero, bare segments.
+fn swept_segments_contact(first: (Vec2, Vec2, f32), second: (Vec2, Vec2, f32), fallback_normal: Vec2) -> Option<CollisionData> {
+    let (a_1, a_2, radius_1) = first;
+    let (b_1, b_2, radius_2) = second;
+    if segments_intersect(a_1, a_2, b_1, b_2) {
+        // Crossing segments have no closest points, SAT over both segment normals
+        let mut normal = Vec2::ZERO;
+        let mut penetration_depth = f32::MAX;
+        let axes = [
+            (a_2 - a_1).perp().normalize_or_zero(),
+            (b_2 - b_1).perp().normalize_or_zero(),
+            fallback_normal,
+        ];
+        for axis in axes.into_iter().filter(|a| *a != Vec2::ZERO) {
+            let (min_1, max_1) = project_capsule(a_1, a_2, radius_1, axis);
+            let (min_2, max_2) = project_capsule(b_1, b_2, radius_2, axis);
+            let depth = sat_is_overlaping(min_1, max_1, min_2, max_2)?;
+            if depth < penetration_depth {
+                penetration_depth = depth;
+                normal = axis;
+            }
+        }
+        let centers = (a_1 + a_2 - b_1 - b_2) / 2.;
+        if centers.dot(normal) < 0. {
+            normal = -normal;
+        }
+        let deepest = if a_1.dot(normal) < a_2.dot(normal) { a_1 } else { a_2 };
+        return Some(CollisionData {
+            unit_normal: normal,
+            penetration_depth,
+            contact_point: deepest - normal * (radius_1 - penetration_depth / 2.),
+        });
+    }
+    let (closest_1, closest_2) = closest_points_between_segments(a_1, a_2, b_1, b_2);
+    disc_contact(closest_1, radius_1, closest_2, radius_2, fallback_normal)
+}
+
+/// Ray against the world aligned box `min`..`max` using the slab method.
+pub fn cast_ray_bounds(min: Vec2, max: Vec2, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+    let mut enter = 0.;
+    let mut exit = max_toi;
+    let mut normal = Vec2::ZERO;
+    for (axis, unit) in [Vec2::X, Vec2::Y].into_iter().enumerate() {
+        if dir[axis].abs() <= f32::EPSILON {
+            // Parallel to the slab, either always inside it or never
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let to_min = (min[axis] - origin[axis]) / dir[axis];
+        let to_max = (max[axis] - origin[axis]) / dir[axis];
+        let (near, far, near_normal) = if to_min < to_max { (to_min, to_max, -unit) } else { (to_max, to_min, unit) };
+        if near > enter {
+            enter = near;
+            normal = near_normal;
+        }
+        exit = exit.min(far);
+        if enter > exit {
+            return None;
+        }
+    }
+    Some(RayIntersection { toi: enter, normal })
+}
+
+fn cast_ray_circle(center: Vec2, radius: f32, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+    let offset = origin - center;
+    let c = offset.length_squared() - radius * radius;
+    if c <= 0. {
+        return Some(RayIntersection { toi: 0., normal: Vec2::ZERO });
+    }
+    let a = dir.length_squared();
+    let b = offset.dot(dir);
+    let discriminant = b * b - a * c;
+    // Pointing away or missing
+    if b >= 0. || discriminant < 0. || a <= f32::EPSILON {
+        return None;
+    }
+    let toi = (-b - discriminant.sqrt()) / a;
+    if toi > max_toi {
+        return None;
+    }
+    Some(RayIntersection { toi, normal: (offset + dir * toi) / radius })
+}
+
+/// Ray against a counter clockwise convex polygon, clipping it by every edge.
+fn cast_ray_convex(verts: &[Vec2], origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+    let mut enter = 0.;
+    let mut exit = max_toi;
+    let mut normal = Vec2::ZERO;
+    for (i, edge_normal) in edge_normals(verts).enumerate() {
+        let distance = edge_normal.dot(verts[i] - origin);
+        let speed = edge_normal.dot(dir);
+        if speed.abs() <= f32::EPSILON {
+            // Parallel to the edge and outside of it
+            if distance < 0. {
+                return None;
+            }
+            continue;
+        }
+        let toi = distance / speed;
+        if speed < 0. {
+            if toi > enter {
+                enter = toi;
+                normal = edge_normal;
+            }
+        } else {
+            exit = exit.min(toi);
+        }
+        if enter > exit {
+            return None;
+        }
+    }
+    Some(RayIntersection { toi: enter, normal })
+}
+
+/// Ray against the segment `a`..`b`, the normal faces the ray.
+fn cast_ray_segment(a: Vec2, b: Vec2, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+    let edge = b - a;
+    let denominator = dir.perp_dot(edge);
+    if denominator.abs() <= f32::EPSILON {
+        return None;
+    }
+    let toi = (a - origin).perp_dot(edge) / denominator;
+    let along = (a - origin).perp_dot(dir) / denominator;
+    if toi < 0. || toi > max_toi || !(0. ..=1.).contains(&along) {
+        return None;
+    }
+    let normal = edge.perp().normalize();
+    Some(RayIntersection { toi, normal: if normal.dot(dir) > 0. { -normal } else { normal } })
+}
+
+/// Two sided line segment between two points relative to its transform.
+///
+/// Meant for static level geometry, it has no area so two segments never collide.
+pub struct Segment {
+    pub a: Vec2,
+    pub b: Vec2,
+}
+
+impl Segment {
+    pub fn new(a: Vec2, b: Vec2) -> Self {
+        Self { a, b }
+    }
+
+    /// World space end points.
+    pub fn get_world_endpoints(&self, self_transform: &Transform) -> (Vec2, Vec2) {
+        (
+            self_transform.transform_point(self.a.extend(0.)).truncate(),
+            self_transform.transform_point(self.b.extend(0.)).truncate(),
+        )
+    }
+
+    pub fn get_extents_on_world_axes(&self, self_transform: &Transform) -> (Vec2, Vec2) {
+        let (a, b) = self.get_world_endpoints(self_transform);
+        (a.min(b), a.max(b))
+    }
+
+    /// Hits either side of the segment.
+    pub fn cast_ray(&self, self_transform: &Transform, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+        let (a, b) = self.get_world_endpoints(self_transform);
+        cast_ray_segment(a, b, origin, dir, max_toi)
+    }
+}
+
+/// Why a vertex list was rejected by [`Polyline::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolylineError {
+    /// Fewer than two vertices.
+    TooFewVertices,
+    /// Two consecutive vertices on top of each other.
+    Degenerate,
+}
+
+impl std::fmt::Display for PolylineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolylineError::TooFewVertices => write!(f, "a polyline needs at least two vertices"),
+            PolylineError::Degenerate => write!(f, "the polyline repeats a vertex"),
+        }
+    }
+}
+
+impl std::error::Error for PolylineError {}
+
+/// Chain of connected segments for static terrain, one sided.
+///
+/// Shapes collide with the left side of each segment as seen walking from one vertex
+/// to the next, so a chain listed from left to right faces up. Shapes whose centre is
+/// behind a segment pass through it.
+///
+/// A shape sliding from one segment onto the next can touch the shared vertex before
+/// the face of the next segment and get stopped by it. Vertex contacts are therefore
+/// only kept where the chain bends away from the shape, everywhere else they are
+/// replaced by a contact along the segment normal. The ghost vertices set with
+/// [`Polyline::with_ghost_vertices`] do the same across the ends of the chain, so
+/// several chains can be joined seamlessly.
+pub struct Polyline {
+    verteces: Vec<Vec2>,
+    ghost_previous: Option<Vec2>,
+    ghost_next: Option<Vec2>,
+}
+
+impl Polyline {
+    pub fn new(verteces: Vec<Vec2>) -> Result<Self, PolylineError> {
+        if verteces.len() < 2 {
+            return Err(PolylineError::TooFewVertices);
+        }
+        if verteces.windows(2).any(|w| w[0].distance_squared(w[1]) <= f32::EPSILON) {
+            return Err(PolylineError::Degenerate);
+        }
+        Ok(Self {
+            verteces,
+            ghost_previous: None,
+            ghost_next: None,
+        })
+    }
+
+    /// Sets the vertices before the first and after the last vertex, usually those of
+    /// the chains connected at either end. They are never collided with, they only
+    /// decide how contacts at the end vertices are handled.
+    pub fn with_ghost_vertices(mut self, previous: Option<Vec2>, next: Option<Vec2>) -> Self {
+        self.ghost_previous = previous;
+        self.ghost_next = next;
+        self
+    }
+
+    pub fn verteces(&self) -> &[Vec2] {
+        &self.verteces
+    }
+
+    pub fn get_world_verteces(&self, self_transform: &Transform) -> Vec<Vec2> {
+        self.verteces
+            .iter()
+            .map(|v| self_transform.transform_point(v.extend(0.)).truncate())
+            .collect()
+    }
+
+    pub fn get_extents_on_world_axes(&self, self_transform: &Transform) -> (Vec2, Vec2) {
+        self.get_world_verteces(self_transform)
+            .into_iter()
+            .fold((Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)), |(min, max), v| {
+                (min.min(v), max.max(v))
+            })
+    }
+
+    /// Only hits the segments from their collision side, like any other contact.
+    pub fn cast_ray(&self, self_transform: &Transform, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+        self.get_world_verteces(self_transform)
+            .windows(2)
+            .filter(|edge| dir.dot((edge[1] - edge[0]).perp()) < 0.)
+            .filter_map(|edge| cast_ray_segment(edge[0], edge[1], origin, dir, max_toi))
+            .min_by(|h_1, h_2| h_1.toi.total_cmp(&h_2.toi))
+    }
+
+    /// Deepest contact between the chain and a shape, the normal points from the chain
+    /// to the shape.
+    ///
+    /// `edge_contact` tests the shape against a single two sided segment, with the normal
+    /// pointing towards the shape, and `support` returns the point of the shape furthest
+    /// along a direction.
+    fn contact_with(
+        &self,
+        self_transform: &Transform,
+        center: Vec2,
+        support: impl Fn(Vec2) -> Vec2,
+        edge_contact: impl Fn(Vec2, Vec2) -> Option<CollisionData>,
+    ) -> Option<CollisionData> {
+        let points = self.get_world_verteces(self_transform);
+        let to_world = |v: Vec2| self_transform.transform_point(v.extend(0.)).truncate();
+        let ghost_previous = self.ghost_previous.map(to_world);
+        let ghost_next = self.ghost_next.map(to_world);
+
+        let mut deepest: Option<CollisionData> = None;
+        for i in 0..points.len() - 1 {
+            let (v_1, v_2) = (points[i], points[i + 1]);
+            let edge = v_2 - v_1;
+            let normal = edge.perp().normalize_or_zero();
+            if normal == Vec2::ZERO || (center - v_1).dot(normal) < 0. {
+                continue;
+            }
+            let Some(mut contact) = edge_contact(v_1, v_2) else {
+                continue;
+            };
+
+            if contact.unit_normal.dot(normal) < 1. - 1e-4 {
+                // Touching an end vertex, only real if the chain bends away there
+                let bends_away = if contact.unit_normal.dot(edge) < 0. {
+                    let previous = if i > 0 { Some(points[i - 1]) } else { ghost_previous };
+                    previous.map(|v_0| (v_1 - v_0).perp_dot(edge) < 0.)
+                } else {
+                    let next = points.get(i + 2).copied().or(ghost_next);
+                    next.map(|v_3| edge.perp_dot(v_3 - v_2) < 0.)
+                };
+                if bends_away == Some(false) {
+                    let lowest = support(-normal);
+                    let penetration_depth = (v_1 - lowest).dot(normal);
+                    if penetration_depth <= 0. {
+                        continue;
+                    }
+                    contact = CollisionData {
+                        unit_normal: normal,
+                        penetration_depth,
+                        contact_point: lowest + normal * penetration_depth / 2.,
+                    };
+                }
+            }
+
+            match &mut deepest {
+                // Resting across a vertex between two flat segments, keep the contact
+                // between both halves so the shape does not tip over the vertex
+                Some(d)
+                    if d.unit_normal.dot(contact.unit_normal) > 1. - 1e-4
+                        && (d.penetration_depth - contact.penetration_depth).abs() < 1e-3 =>
+                {
+                    d.contact_point = (d.contact_point + contact.contact_point) / 2.;
+                }
+                Some(d) if d.penetration_depth >= contact.penetration_depth => {}
+                _ => deepest = Some(contact),
+            }
+        }
+        deepest
+    }
+
+    /// Contact with any convex shape, the normal points from the shape to the chain.
+    pub fn is_colliding_with_convex(
+        &self,
+        self_transform: &Transform,
+        other: &dyn SupportMap,
+        other_transform: &Transform,
+    ) -> Option<CollisionData> {
+        self.contact_with(
+            self_transform,
+            other_transform.translation.truncate(),
+            |direction| other.support(other_transform, direction) + direction.normalize_or_zero() * other.radius(),
+            |v_1, v_2| gjk::collide(other, other_transform, &Segment::new(v_1, v_2), &Transform::default()),
+        )
+        .map(CollisionData::flipped)
+    }
+}
+
+/// One shape of a [`Compound`], placed relative to the compound's origin.
+pub struct CompoundChild {
+    pub shape: Primitives,
+    pub offset: Vec2,
+    /// Rotation around the child's own origin in radians.
+    pub rotation: f32,
+    pub mass: f32,
+}
+
+impl CompoundChild {
+    pub fn new(shape: Primitives, offset: Vec2, rotation: f32, mass: f32) -> Self {
+        Self {
+            shape,
+            offset,
+            rotation,
+            mass,
+        }
+    }
+
+    /// Transform of the child given the transform of its compound.
+    pub fn get_world_transform(&self, compound_transform: &Transform) -> Transform {
+        compound_transform.mul_transform(
+            Transform::from_translation(self.offset.extend(0.)).with_rotation(Quat::from_rotation_z(self.rotation)),
+        )
+    }
+}
+
+/// Why a list of children was rejected by [`Compound::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompoundError {
+    /// No children at all.
+    Empty,
+    /// The masses of the children do not add up to more than zero.
+    NoMass,
+}
+
+impl std::fmt::Display for CompoundError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompoundError::Empty => write!(f, "a compound needs at least one child"),
+            CompoundError::NoMass => write!(f, "the children of a compound need a positive total mass"),
+        }
+    }
+}
+
+impl std::error::Error for CompoundError {}
+
+/// Several shapes moving as one body, e.g. an L shaped platform or a vehicle.
+///
+/// The narrowphase runs for every child on its own, so a compound touching another
+/// body can produce one contact per child. The children hit are reported through
+/// [`ContactId`](crate::solver::ContactId).
+pub struct Compound {
+    children: Vec<CompoundChild>,
+}
+
+impl Compound {
+    /// The children are shifted so their mass weighted centre lies on the origin, which
+    /// the solver treats as the centre of mass of the body.
+    pub fn new(mut children: Vec<CompoundChild>) -> Result<Self, CompoundError> {
+        if children.is_empty() {
+            return Err(CompoundError::Empty);
+        }
+        let mass: f32 = children.iter().map(|c| c.mass).sum();
+        if !mass.is_finite() || mass <= 0. {
+            return Err(CompoundError::NoMass);
+        }
+        let center = children.iter().map(|c| c.offset * c.mass).sum::<Vec2>() / mass;
+        for child in &mut children {
+            child.offset -= center;
+        }
+        Ok(Self { children })
+    }
+
+    pub fn children(&self) -> &[CompoundChild] {
+        &self.children
+    }
+
+    /// Summed mass of all children.
+    pub fn mass(&self) -> f32 {
+        self.children.iter().map(|c| c.mass).sum()
+    }
+
+    /// Moment of inertia around the origin, adding up the children with the parallel
+    /// axis theorem.
+    pub fn moment_of_inertia(&self) -> f32 {
+        self.children
+            .iter()
+            .map(|c| c.shape.moment_of_inertia(c.mass) + c.mass * c.offset.length_squared())
+            .sum()
+    }
+
+    pub fn get_extents_on_world_axes(&self, self_transform: &Transform) -> (Vec2, Vec2) {
+        self.children
+            .iter()
+            .map(|c| c.shape.get_world_bounds(&c.get_world_transform(self_transform)))
+            .fold((Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)), |(min, max), (c_min, c_max)| {
+                (min.min(c_min), max.max(c_max))
+            })
+    }
+
+    pub fn cast_ray(&self, self_transform: &Transform, origin: Vec2, dir: Vec2, max_toi: f32) -> Option<RayIntersection> {
+        self.children
+            .iter()
+            .filter_map(|c| c.shape.cast_ray(&c.get_world_transform(self_transform), origin, dir, max_toi))
+            .min_by(|h_1, h_2| h_1.toi.total_cmp(&h_2.toi))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn box_at(x: f32, y: f32, angle: f32) -> Vec<Vec2> {
+        let transform = Transform::from_xyz(x, y, 0.).with_rotation(Quat::from_rotation_z(angle));
+        OBB::new(20., 20.).get_verteces(&transform).to_vec()
+    }
+
+    #[test]
+    fn clip_box_resting_on_box() {
+        let manifold = clip_convex(&box_at(4., 19., 0.), 0., &box_at(0., 0., 0.), 0.).unwrap();
+        assert!((manifold.unit_normal - Vec2::Y).length() < 1e-4, "{manifold:?}");
+        assert_eq!(manifold.points.len(), 2, "{manifold:?}");
+        for point in &manifold.points {
+            assert!((point.penetration_depth - 1.).abs() < 1e-4, "{manifold:?}");
+            assert!((point.point.y - 9.5).abs() < 1e-4, "{manifold:?}");
+        }
+        // Clipped to the overlap of both top and bottom faces
+        let mut xs: Vec<f32> = manifold.points.iter().map(|p| p.point.x).collect();
+        xs.sort_by(f32::total_cmp);
+        assert!((xs[0] + 6.).abs() < 1e-4 && (xs[1] - 10.).abs() < 1e-4, "{manifold:?}");
+        assert_ne!(manifold.points[0].feature, manifold.points[1].feature);
+    }
+
+    #[test]
+    fn box_tests_merge_the_clipped_points() {
+        let obb = OBB::new(20., 20.);
+        let t_1 = Transform::from_xyz(4., 19., 0.);
+        let contact = obb.is_colliding_with_obb(&t_1, &obb, &Transform::default()).unwrap();
+        assert!((contact.unit_normal - Vec2::Y).length() < 1e-4, "{contact:?}");
+        assert!((contact.penetration_depth - 1.).abs() < 1e-4, "{contact:?}");
+        assert!((contact.contact_point - Vec2::new(2., 9.5)).length() < 1e-4, "{contact:?}");
+
+        let aabb = AABB::new(20., 20.);
+        let from_aabb = aabb.is_colliding_with_obb(&t_1, &obb, &Transform::default()).unwrap();
+        assert!((from_aabb.contact_point - contact.contact_point).length() < 1e-4, "{from_aabb:?}");
+        let square = ConvexPolygon::new(obb.get_verteces(&Transform::default()).to_vec()).unwrap();
+        let from_polygon = square.is_colliding_with_obb(&t_1, &obb, &Transform::default()).unwrap();
+        assert!((from_polygon.contact_point - contact.contact_point).length() < 1e-4, "{from_polygon:?}");
+        assert!(square.is_colliding_with_polygon(&Transform::from_xyz(0., 21., 0.), &square, &Transform::default()).is_none());
+    }
+
+    #[test]
+    fn anchors_follow_the_bodies() {
+        let t_1 = Transform::from_xyz(4., 19., 0.).with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+        let t_2 = Transform::default();
+        let mut manifold = clip_convex(&box_at(4., 19., 0.), 0., &box_at(0., 0., 0.), 0.).unwrap();
+        manifold.set_local_anchors(&t_1, &t_2);
+        for point in &manifold.points {
+            assert!((t_1.transform_point(point.local_anchor_1.extend(0.)).truncate() - point.point).length() < 1e-4);
+            assert!((point.local_anchor_2 - point.point).length() < 1e-4);
+        }
+        let flipped = manifold.clone().flipped();
+        assert_eq!(flipped.points[0].local_anchor_1, manifold.points[0].local_anchor_2);
+        assert_eq!(flipped.unit_normal, -manifold.unit_normal);
+    }
+
+    #[test]
+    fn clip_tilted_box_touches_with_one_corner() {
+        let manifold = clip_convex(&box_at(0., 22., 0.3), 0., &box_at(0., 0., 0.), 0.).unwrap();
+        assert!((manifold.unit_normal - Vec2::Y).length() < 1e-4, "{manifold:?}");
+        assert_eq!(manifold.points.len(), 1, "{manifold:?}");
+        assert!(clip_convex(&box_at(0., 30., 0.3), 0., &box_at(0., 0., 0.), 0.).is_none());
+    }
+
+    #[test]
+    fn clip_capsule_lying_on_box() {
+        let capsule = [Vec2::new(-8., 14.), Vec2::new(8., 14.)];
+        let manifold = clip_convex(&capsule, 5., &box_at(0., 0., 0.), 0.).unwrap();
+        assert!((manifold.unit_normal - Vec2::Y).length() < 1e-4, "{manifold:?}");
+        assert_eq!(manifold.points.len(), 2, "{manifold:?}");
+        for point in &manifold.points {
+            assert!((point.penetration_depth - 1.).abs() < 1e-4, "{manifold:?}");
+            assert!((point.point.y - 9.5).abs() < 1e-4, "{manifold:?}");
+        }
+    }
+
+    #[test]
+    fn polygon_rejects_concave_outline() {
+        let arrow = vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(2., 2.), Vec2::new(0., 10.)];
+        assert_eq!(ConvexPolygon::new(arrow).err(), Some(PolygonError::NotConvex));
+    }
+
+    #[test]
+    fn polygon_rejects_self_intersecting_outline() {
+        // Every corner of a pentagram turns the same way, but it winds around twice
+        let pentagram = (0..5)
+            .map(|i| {
+                let angle = i as f32 * 4. * std::f32::consts::PI / 5.;
+                Vec2::new(angle.cos(), angle.sin()) * 10.
+            })
+            .collect();
+        assert_eq!(ConvexPolygon::new(pentagram).err(), Some(PolygonError::NotConvex));
+
+        let bow_tie = vec![Vec2::new(0., 0.), Vec2::new(10., 10.), Vec2::new(10., 0.), Vec2::new(0., 10.)];
+        assert!(ConvexPolygon::new(bow_tie).is_err());
+    }
+
+    #[test]
+    fn polyline_rejects_degenerate_chains() {
+        assert_eq!(Polyline::new(vec![Vec2::ZERO]).err(), Some(PolylineError::TooFewVertices));
+        let repeated = vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(10., 0.)];
+        assert_eq!(Polyline::new(repeated).err(), Some(PolylineError::Degenerate));
+        assert!(Polyline::new(vec![Vec2::new(0., 0.), Vec2::new(10., 0.)]).is_ok());
+    }
+
+    fn chain(verteces: &[(f32, f32)]) -> Polyline {
+        Polyline::new(verteces.iter().map(|&(x, y)| Vec2::new(x, y)).collect()).unwrap()
+    }
+
+    fn box_on(chain: &Polyline, x: f32, y: f32) -> Option<CollisionData> {
+        chain.is_colliding_with_convex(&Transform::default(), &OBB::new(10., 10.), &Transform::from_xyz(x, y, 0.))
+    }
+
+    #[test]
+    fn polyline_box_slides_over_flat_vertex() {
+        let flat = chain(&[(-50., 0.), (0., 0.), (50., 0.)]);
+        // Sunk 0.5 into the chain, sliding right across the vertex at the origin
+        for step in 0..=20 {
+            let x = -10. + step as f32;
+            let contact = box_on(&flat, x, 4.5).unwrap();
+            assert!(contact.unit_normal.abs_diff_eq(Vec2::NEG_Y, 1e-4), "x {x}: {contact:?}");
+            assert!((contact.penetration_depth - 0.5).abs() < 1e-3, "x {x}: {contact:?}");
+        }
+    }
+
+    #[test]
+    fn polyline_keeps_convex_corner_contact() {
+        // Flat, then sloping down to the right
+        let corner = chain(&[(-50., 0.), (0., 0.), (50., -50.)]);
+        let circle = CircleCollider::new(5.);
+        let contact = corner
+            .is_colliding_with_convex(&Transform::default(), &circle, &Transform::from_xyz(2., 4., 0.))
+            .unwrap();
+        let expected = -Vec2::new(2., 4.).normalize();
+        assert!(contact.unit_normal.abs_diff_eq(expected, 1e-3), "{contact:?}");
+        assert!((contact.penetration_depth - (5. - 20_f32.sqrt())).abs() < 1e-3, "{contact:?}");
+    }
+
+    #[test]
+    fn polyline_ghost_vertices_join_chains() {
+        // The box rests on the left chain with its corner just over the start of the right one
+        let right = chain(&[(0., 0.), (50., 0.)]);
+        let caught = box_on(&right, -4.9, 4.5).unwrap();
+        assert!(caught.unit_normal.abs_diff_eq(Vec2::X, 1e-4), "{caught:?}");
+
+        let joined = chain(&[(0., 0.), (50., 0.)]).with_ghost_vertices(Some(Vec2::new(-50., 0.)), None);
+        let contact = box_on(&joined, -4.9, 4.5).unwrap();
+        assert!(contact.unit_normal.abs_diff_eq(Vec2::NEG_Y, 1e-4), "{contact:?}");
+        assert!((contact.penetration_depth - 0.5).abs() < 1e-3, "{contact:?}");
+
+        let left = chain(&[(-50., 0.), (0., 0.)]).with_ghost_vertices(None, Some(Vec2::new(50., 0.)));
+        let contact = box_on(&left, 4.9, 4.5).unwrap();
+        assert!(contact.unit_normal.abs_diff_eq(Vec2::NEG_Y, 1e-4), "{contact:?}");
+    }
+
+    #[test]
+    fn polyline_is_one_sided() {
+        let flat = chain(&[(-50., 0.), (50., 0.)]);
+        assert!(box_on(&flat, 0., 3.).is_some());
+        // Same overlap from behind the face
+        assert!(box_on(&flat, 0., -3.).is_none());
+        // Listed the other way round the chain faces down
+        let flipped = chain(&[(50., 0.), (-50., 0.)]);
+        assert!(box_on(&flipped, 0., -3.).is_some());
+        assert!(box_on(&flipped, 0., 3.).is_none());
+    }
+
+    #[test]
+    fn compound_rejects_missing_mass() {
+        assert_eq!(Compound::new(vec![]).err(), Some(CompoundError::Empty));
+        let massless = CompoundChild::new(Primitives::Circle(CircleCollider::new(1.)), Vec2::ZERO, 0., 0.);
+        assert_eq!(Compound::new(vec![massless]).err(), Some(CompoundError::NoMass));
+
+        let compound = Compound::new(vec![
+            CompoundChild::new(Primitives::Circle(CircleCollider::new(1.)), Vec2::new(0., 0.), 0., 1.),
+            CompoundChild::new(Primitives::Circle(CircleCollider::new(1.)), Vec2::new(4., 0.), 0., 3.),
+        ])
+        .unwrap();
+        assert_eq!(compound.children()[0].offset, Vec2::new(-3., 0.));
+        assert_eq!(compound.children()[1].offset, Vec2::new(1., 0.));
+    }
+
+    #[test]
+    fn polygon_is_centred_on_its_centroid() {
+        // Clockwise and away from the origin
+        let triangle = ConvexPolygon::new(vec![Vec2::new(10., 10.), Vec2::new(13., 19.), Vec2::new(16., 10.)]).unwrap();
+        let expected = [Vec2::new(3., -3.), Vec2::new(0., 6.), Vec2::new(-3., -3.)];
+        for (vertex, expected) in triangle.verteces().iter().zip(expected) {
+            assert!((*vertex - expected).length() < 1e-4, "{:?}", triangle.verteces());
+        }
+
+        let square = ConvexPolygon::new(vec![Vec2::new(5., 5.), Vec2::new(7., 5.), Vec2::new(7., 7.), Vec2::new(5., 7.)]).unwrap();
+        // Same as a 2 by 2 box, m * (w^2 + h^2) / 12
+        assert!((square.moment_of_inertia(3.) - 2.).abs() < 1e-4);
+    }
+
+    fn assert_ray_hit(hit: Option<RayIntersection>, toi: f32, normal: Vec2) {
+        let hit = hit.expect("the ray should hit");
+        assert!((hit.toi - toi).abs() < 1e-3, "{hit:?} should be at {toi}");
+        assert!(hit.normal.abs_diff_eq(normal, 1e-3), "{hit:?} should face {normal}");
+    }
+
+    #[test]
+    fn ray_against_bounds() {
+        let (min, max) = (Vec2::new(-10., -5.), Vec2::new(10., 5.));
+        assert_ray_hit(cast_ray_bounds(min, max, Vec2::new(-30., 0.), Vec2::X, 100.), 20., Vec2::NEG_X);
+        assert_ray_hit(cast_ray_bounds(min, max, Vec2::new(0., 25.), Vec2::new(0., -2.), 100.), 10., Vec2::Y);
+        // Diagonal, through the corner region onto the top face
+        assert_ray_hit(cast_ray_bounds(min, max, Vec2::new(-20., 20.), Vec2::new(1., -1.), 100.), 15., Vec2::Y);
+        assert_ray_hit(cast_ray_bounds(min, max, Vec2::new(3., 1.), Vec2::X, 100.), 0., Vec2::ZERO);
+
+        assert!(cast_ray_bounds(min, max, Vec2::new(-30., 0.), Vec2::X, 19.).is_none());
+        assert!(cast_ray_bounds(min, max, Vec2::new(-30., 0.), Vec2::NEG_X, 100.).is_none());
+        assert!(cast_ray_bounds(min, max, Vec2::new(-30., 0.), Vec2::new(1., 1.), 100.).is_none());
+        // Parallel to a slab, inside it or outside of it
+        assert_ray_hit(cast_ray_bounds(min, max, Vec2::new(-30., 5.), Vec2::X, 100.), 20., Vec2::NEG_X);
+        assert!(cast_ray_bounds(min, max, Vec2::new(-30., 6.), Vec2::X, 100.).is_none());
+    }
+
+    #[test]
+    fn ray_against_circle() {
+        let center = Vec2::new(10., 0.);
+        assert_ray_hit(cast_ray_circle(center, 5., Vec2::ZERO, Vec2::X, 100.), 5., Vec2::NEG_X);
+        // Time of impact in multiples of the direction
+        assert_ray_hit(cast_ray_circle(center, 5., Vec2::ZERO, Vec2::new(5., 0.), 100.), 1., Vec2::NEG_X);
+        assert_ray_hit(cast_ray_circle(center, 5., Vec2::new(10., 20.), Vec2::NEG_Y, 100.), 15., Vec2::Y);
+        assert_ray_hit(cast_ray_circle(center, 5., Vec2::new(12., 1.), Vec2::X, 100.), 0., Vec2::ZERO);
+
+        assert!(cast_ray_circle(center, 5., Vec2::ZERO, Vec2::X, 4.).is_none());
+        assert!(cast_ray_circle(center, 5., Vec2::ZERO, Vec2::NEG_X, 100.).is_none());
+        assert!(cast_ray_circle(center, 5., Vec2::new(0., 6.), Vec2::X, 100.).is_none());
+    }
+
+    #[test]
+    fn ray_against_convex() {
+        let square = box_at(0., 0., 0.);
+        assert_ray_hit(cast_ray_convex(&square, Vec2::new(-30., 2.), Vec2::X, 100.), 20., Vec2::NEG_X);
+        let diamond = box_at(0., 0., std::f32::consts::FRAC_PI_4);
+        let corner = 10. * std::f32::consts::SQRT_2;
+        assert_ray_hit(
+            cast_ray_convex(&diamond, Vec2::new(-30., -2.), Vec2::X, 100.),
+            32. - corner,
+            Vec2::new(-1., -1.).normalize(),
+        );
+        assert_ray_hit(cast_ray_convex(&square, Vec2::new(1., 1.), Vec2::Y, 100.), 0., Vec2::ZERO);
+
+        assert!(cast_ray_convex(&square, Vec2::new(-30., 2.), Vec2::X, 19.).is_none());
+        assert!(cast_ray_convex(&square, Vec2::new(-30., 2.), Vec2::NEG_X, 100.).is_none());
+        // Parallel to the top edge, just outside of it and right along it
+        assert!(cast_ray_convex(&square, Vec2::new(-30., 11.), Vec2::X, 100.).is_none());
+        assert_ray_hit(cast_ray_convex(&square, Vec2::new(-30., 10.), Vec2::X, 100.), 20., Vec2::NEG_X);
+    }
+
+    #[test]
+    fn ray_against_segment() {
+        let (a, b) = (Vec2::new(0., -10.), Vec2::new(0., 10.));
+        // Two sided, the normal faces the ray from either side
+        assert_ray_hit(cast_ray_segment(a, b, Vec2::new(-10., 0.), Vec2::X, 100.), 10., Vec2::NEG_X);
+        assert_ray_hit(cast_ray_segment(a, b, Vec2::new(10., 5.), Vec2::new(-2., 0.), 100.), 5., Vec2::X);
+
+        assert!(cast_ray_segment(a, b, Vec2::new(-10., 0.), Vec2::X, 9.).is_none());
+        assert!(cast_ray_segment(a, b, Vec2::new(-10., 0.), Vec2::NEG_X, 100.).is_none());
+        assert!(cast_ray_segment(a, b, Vec2::new(-10., 11.), Vec2::X, 100.).is_none());
+        // Parallel, even along the segment itself
+        assert!(cast_ray_segment(a, b, Vec2::new(0., -20.), Vec2::Y, 100.).is_none());
+    }
+
+    #[test]
+    fn ray_against_shapes() {
+        let at = |x: f32, y: f32, angle: f32| Transform::from_xyz(x, y, 0.).with_rotation(Quat::from_rotation_z(angle));
+        let ray = |shape: &Primitives, transform: &Transform, origin: Vec2, dir: Vec2| shape.cast_ray(transform, origin, dir, 100.);
+
+        let circle = Primitives::Circle(CircleCollider::new(5.));
+        assert_ray_hit(ray(&circle, &at(10., 0., 0.), Vec2::ZERO, Vec2::X), 5., Vec2::NEG_X);
+
+        // An AABB ignores the rotation of its transform
+        let aabb = Primitives::AABB(AABB::new(20., 20.));
+        assert_ray_hit(ray(&aabb, &at(0., 0., 0.7), Vec2::new(-30., 0.), Vec2::X), 20., Vec2::NEG_X);
+
+        let obb = Primitives::OBB(OBB::new(20., 20.));
+        let corner = 10. * std::f32::consts::SQRT_2;
+        assert_ray_hit(
+            ray(&obb, &at(0., 0., std::f32::consts::FRAC_PI_4), Vec2::new(-30., -2.), Vec2::X),
+            32. - corner,
+            Vec2::new(-1., -1.).normalize(),
+        );
+        assert!(ray(&obb, &at(0., 0., std::f32::consts::FRAC_PI_4), Vec2::new(-30., 15.), Vec2::X).is_none());
+
+        // Upright capsule 20 long with radius 5, its side then its top cap
+        let capsule = Primitives::Capsule(Capsule::new(20., 5.));
+        assert_ray_hit(ray(&capsule, &at(0., 0., 0.), Vec2::new(-20., 3.), Vec2::X), 15., Vec2::NEG_X);
+        assert_ray_hit(ray(&capsule, &at(0., 0., 0.), Vec2::new(0., 30.), Vec2::NEG_Y), 15., Vec2::Y);
+        assert_ray_hit(ray(&capsule, &at(0., 0., 0.), Vec2::new(0., 12.), Vec2::NEG_Y), 0., Vec2::ZERO);
+        assert!(ray(&capsule, &at(0., 0., 0.), Vec2::new(-20., 3.), Vec2::NEG_X).is_none());
+
+        // One sided, facing up
+        let chain = Primitives::Polyline(Polyline::new(vec![Vec2::new(-50., 0.), Vec2::new(0., 0.), Vec2::new(50., 10.)]).unwrap());
+        assert_ray_hit(ray(&chain, &at(0., 0., 0.), Vec2::new(-20., 20.), Vec2::NEG_Y), 20., Vec2::Y);
+        assert!(ray(&chain, &at(0., 0., 0.), Vec2::new(-20., -20.), Vec2::Y).is_none());
+        // Along the first segment, only the start of the second one is hit
+        assert_ray_hit(ray(&chain, &at(0., 0., 0.), Vec2::new(-80., 0.), Vec2::X), 80., Vec2::new(-10., 50.).normalize());
+        assert!(chain.cast_ray(&at(0., 0., 0.), Vec2::new(-80., 0.), Vec2::X, 70.).is_none());
+
+        // The nearest child of a compound
+        let compound = Primitives::Compound(
+            Compound::new(vec![
+                CompoundChild::new(Primitives::Circle(CircleCollider::new(5.)), Vec2::new(-10., 0.), 0., 1.),
+                CompoundChild::new(Primitives::Circle(CircleCollider::new(5.)), Vec2::new(10., 0.), 0., 1.),
+            ])
+            .unwrap(),
+        );
+        assert_ray_hit(ray(&compound, &at(0., 0., 0.), Vec2::new(30., 0.), Vec2::NEG_X), 15., Vec2::X);
+        assert_ray_hit(ray(&compound, &at(0., 0., 0.), Vec2::new(-30., 0.), Vec2::X), 15., Vec2::NEG_X);
     }
 }
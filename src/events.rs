@@ -0,0 +1,76 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::{collision_primitives::CollisionData, solver::{ContactConstraint, ContactId}};
+
+/// Sent the first step two bodies touch, including overlaps with a
+/// [`Sensor`](crate::rigidbody::Sensor).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionStarted(pub Entity, pub Entity);
+
+/// Sent the first step two bodies that touched in the previous step no longer do, or one
+/// of them was removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionEnded(pub Entity, pub Entity);
+
+/// A single contact point solved this step, sent every step the bodies keep touching.
+/// Never sent for sensors since their contacts are not solved.
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    /// The bodies, the [`Compound`](crate::collision_primitives::Compound) children and
+    /// the feature this contact is between. Stays the same from one step to the next
+    /// while the contact persists.
+    pub id: ContactId,
+    /// The normal points from `id.entity_2` to `id.entity_1`.
+    pub collision_data: CollisionData,
+    /// Total impulse the solver applied to `id.entity_1` through this contact, the opposite
+    /// was applied to `id.entity_2`.
+    pub impulse: Vec2,
+}
+
+/// Pairs of bodies touching as of the last step, smaller entity first. Used to tell
+/// which collisions started or ended.
+#[derive(Resource, Debug, Default)]
+pub struct CollidingPairs(pub HashSet<(Entity, Entity)>);
+
+/// Where a step sends its collision events.
+pub(crate) struct CollisionEventWriters<'a, 'w, 's> {
+    pub pairs: &'a mut CollidingPairs,
+    pub started: EventWriter<'w, 's, CollisionStarted>,
+    pub ended: EventWriter<'w, 's, CollisionEnded>,
+    pub contacts: EventWriter<'w, 's, Contact>,
+}
+
+impl<'a, 'w, 's> CollisionEventWriters<'a, 'w, 's> {
+    /// Sends a [`Contact`] for every touching contact and compares the touching pairs,
+    /// together with the overlapping `sensor_pairs`, with those of the last step.
+    /// Speculative contacts do not count as touching.
+    pub fn send(&mut self, contacts: &[ContactConstraint], sensor_pairs: &[(Entity, Entity)]) {
+        let mut touching = HashSet::default();
+        for &(entity_1, entity_2) in sensor_pairs {
+            let pair = (entity_1.min(entity_2), entity_1.max(entity_2));
+            if touching.insert(pair) && !self.pairs.0.contains(&pair) {
+                self.started.send(CollisionStarted(pair.0, pair.1));
+            }
+        }
+        for contact in contacts.iter().filter(|c| c.collision_data.penetration_depth >= 0.) {
+            let (entity_1, entity_2) = (contact.id.entity_1, contact.id.entity_2);
+            self.contacts.send(Contact {
+                id: contact.id,
+                collision_data: contact.collision_data,
+                impulse: contact.applied_impulse(),
+            });
+            let pair = (entity_1.min(entity_2), entity_1.max(entity_2));
+            if touching.insert(pair) && !self.pairs.0.contains(&pair) {
+                self.started.send(CollisionStarted(pair.0, pair.1));
+            }
+        }
+
+        // Sorted so the events do not depend on the order of the hash set
+        let mut ended: Vec<_> = self.pairs.0.difference(&touching).copied().collect();
+        ended.sort_unstable();
+        for (entity_1, entity_2) in ended {
+            self.ended.send(CollisionEnded(entity_1, entity_2));
+        }
+        self.pairs.0 = touching;
+    }
+}
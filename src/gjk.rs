@@ -0,0 +1,425 @@
+use bevy::prelude::*;
+
+use crate::collision_primitives::{Capsule, CircleCollider, CollisionData, ConvexPolygon, Segment, AABB, OBB};
+
+const MAX_ITERATIONS: usize = 32;
+const TOLERANCE: f32 = 1e-4;
+
+/// A convex shape described by its support function, which is all [`distance`] and
+/// [`collide`] need to know about it.
+///
+/// Rounded shapes are split into a core and a radius: a circle is a point grown by its
+/// radius and a capsule a segment grown by its radius. GJK and EPA run on the cores,
+/// which keeps them exact and quick to converge on round shapes.
+pub trait SupportMap {
+    /// World space point of the core furthest along `direction`.
+    fn support(&self, transform: &Transform, direction: Vec2) -> Vec2;
+
+    /// Radius the core is grown by.
+    fn radius(&self) -> f32 {
+        0.
+    }
+}
+
+impl SupportMap for CircleCollider {
+    fn support(&self, transform: &Transform, _direction: Vec2) -> Vec2 {
+        transform.translation.truncate()
+    }
+
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+impl SupportMap for Capsule {
+    fn support(&self, transform: &Transform, direction: Vec2) -> Vec2 {
+        let (a, b) = self.get_segment(transform);
+        if a.dot(direction) > b.dot(direction) { a } else { b }
+    }
+
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+impl SupportMap for Segment {
+    fn support(&self, transform: &Transform, direction: Vec2) -> Vec2 {
+        let (a, b) = self.get_world_endpoints(transform);
+        if a.dot(direction) > b.dot(direction) { a } else { b }
+    }
+}
+
+impl SupportMap for AABB {
+    fn support(&self, transform: &Transform, direction: Vec2) -> Vec2 {
+        let (min, max) = self.get_extents_as_global_vectors(transform);
+        Vec2::new(
+            if direction.x > 0. { max.x } else { min.x },
+            if direction.y > 0. { max.y } else { min.y },
+        )
+    }
+}
+
+impl SupportMap for OBB {
+    fn support(&self, transform: &Transform, direction: Vec2) -> Vec2 {
+        furthest(&self.get_verteces(transform), direction)
+    }
+}
+
+impl SupportMap for ConvexPolygon {
+    fn support(&self, transform: &Transform, direction: Vec2) -> Vec2 {
+        furthest(&self.get_world_verteces(transform), direction)
+    }
+}
+
+fn furthest(verts: &[Vec2], direction: Vec2) -> Vec2 {
+    verts
+        .iter()
+        .copied()
+        .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+        .unwrap_or_default()
+}
+
+/// Closest points between two separated shapes, see [`distance`].
+#[derive(Debug, Clone, Copy)]
+pub struct ClosestPoints {
+    pub point_1: Vec2,
+    pub point_2: Vec2,
+    /// Points from the second shape towards the first, set even when they only touch.
+    pub normal: Vec2,
+    pub distance: f32,
+}
+
+/// A point of the Minkowski difference together with the points of both cores it
+/// came from, so witness points can be recovered.
+#[derive(Debug, Clone, Copy)]
+struct SimplexVertex {
+    point_1: Vec2,
+    point_2: Vec2,
+    point: Vec2,
+}
+
+struct Shape<'a> {
+    shape: &'a dyn SupportMap,
+    transform: &'a Transform,
+}
+
+fn support(shape_1: &Shape, shape_2: &Shape, direction: Vec2) -> SimplexVertex {
+    let point_1 = shape_1.shape.support(shape_1.transform, direction);
+    let point_2 = shape_2.shape.support(shape_2.transform, -direction);
+    SimplexVertex {
+        point_1,
+        point_2,
+        point: point_1 - point_2,
+    }
+}
+
+/// Result of running GJK on the cores.
+enum Gjk {
+    /// Closest points on the cores and their distance.
+    Separated(ClosestPoints),
+    /// The cores overlap, holds the last simplex for EPA to start from.
+    Overlapping(Vec<SimplexVertex>),
+}
+
+/// Reduces `simplex` to the smallest sub simplex closest to the origin and returns the
+/// barycentric weights of that closest point, following Box2D's `b2Simplex`.
+fn solve_simplex(simplex: &mut Vec<SimplexVertex>) -> Vec<f32> {
+    match simplex.len() {
+        1 => vec![1.],
+        2 => {
+            let (w_1, w_2) = (simplex[0].point, simplex[1].point);
+            let edge = w_2 - w_1;
+            let d_2 = -w_1.dot(edge);
+            if d_2 <= 0. {
+                simplex.truncate(1);
+                return vec![1.];
+            }
+            let d_1 = w_2.dot(edge);
+            if d_1 <= 0. {
+                simplex.remove(0);
+                return vec![1.];
+            }
+            vec![d_1 / (d_1 + d_2), d_2 / (d_1 + d_2)]
+        }
+        _ => {
+            let (w_1, w_2, w_3) = (simplex[0].point, simplex[1].point, simplex[2].point);
+            let (e_12, e_13, e_23) = (w_2 - w_1, w_3 - w_1, w_3 - w_2);
+            let (d12_1, d12_2) = (w_2.dot(e_12), -w_1.dot(e_12));
+            let (d13_1, d13_2) = (w_3.dot(e_13), -w_1.dot(e_13));
+            let (d23_1, d23_2) = (w_3.dot(e_23), -w_2.dot(e_23));
+            let n_123 = e_12.perp_dot(e_13);
+            let d123_1 = n_123 * w_2.perp_dot(w_3);
+            let d123_2 = n_123 * w_3.perp_dot(w_1);
+            let d123_3 = n_123 * w_1.perp_dot(w_2);
+
+            let keep = |simplex: &mut Vec<SimplexVertex>, indices: &[usize]| {
+                *simplex = indices.iter().map(|i| simplex[*i]).collect();
+            };
+            if d12_2 <= 0. && d13_2 <= 0. {
+                keep(simplex, &[0]);
+                vec![1.]
+            } else if d12_1 > 0. && d12_2 > 0. && d123_3 <= 0. {
+                keep(simplex, &[0, 1]);
+                vec![d12_1 / (d12_1 + d12_2), d12_2 / (d12_1 + d12_2)]
+            } else if d13_1 > 0. && d13_2 > 0. && d123_2 <= 0. {
+                keep(simplex, &[0, 2]);
+                vec![d13_1 / (d13_1 + d13_2), d13_2 / (d13_1 + d13_2)]
+            } else if d12_1 <= 0. && d23_2 <= 0. {
+                keep(simplex, &[1]);
+                vec![1.]
+            } else if d13_1 <= 0. && d23_1 <= 0. {
+                keep(simplex, &[2]);
+                vec![1.]
+            } else if d23_1 > 0. && d23_2 > 0. && d123_1 <= 0. {
+                keep(simplex, &[1, 2]);
+                vec![d23_1 / (d23_1 + d23_2), d23_2 / (d23_1 + d23_2)]
+            } else {
+                let sum = d123_1 + d123_2 + d123_3;
+                vec![d123_1 / sum, d123_2 / sum, d123_3 / sum]
+            }
+        }
+    }
+}
+
+fn gjk(shape_1: &Shape, shape_2: &Shape) -> Gjk {
+    let initial = shape_1.transform.translation.truncate() - shape_2.transform.translation.truncate();
+    let initial = if initial.length_squared() > TOLERANCE { initial } else { Vec2::X };
+    let mut simplex = vec![support(shape_1, shape_2, initial)];
+
+    for _ in 0..MAX_ITERATIONS {
+        let weights = solve_simplex(&mut simplex);
+        if simplex.len() == 3 {
+            return Gjk::Overlapping(simplex);
+        }
+        let closest: Vec2 = simplex.iter().zip(&weights).map(|(v, w)| v.point * *w).sum();
+        if closest.length_squared() <= TOLERANCE * TOLERANCE {
+            return Gjk::Overlapping(simplex);
+        }
+
+        let direction = -closest;
+        let vertex = support(shape_1, shape_2, direction);
+        // No further progress towards the origin, the closest point is found
+        let progress = (vertex.point - closest).dot(direction);
+        let duplicate = simplex.iter().any(|v| v.point.distance_squared(vertex.point) <= TOLERANCE * TOLERANCE);
+        if progress <= TOLERANCE * direction.length() || duplicate {
+            let point_1 = simplex.iter().zip(&weights).map(|(v, w)| v.point_1 * *w).sum();
+            let point_2 = simplex.iter().zip(&weights).map(|(v, w)| v.point_2 * *w).sum();
+            return Gjk::Separated(ClosestPoints {
+                point_1,
+                point_2,
+                normal: closest.normalize_or_zero(),
+                distance: closest.length(),
+            });
+        }
+        simplex.push(vertex);
+    }
+
+    let weights = solve_simplex(&mut simplex);
+    let point_1: Vec2 = simplex.iter().zip(&weights).map(|(v, w)| v.point_1 * *w).sum();
+    let point_2: Vec2 = simplex.iter().zip(&weights).map(|(v, w)| v.point_2 * *w).sum();
+    Gjk::Separated(ClosestPoints {
+        point_1,
+        point_2,
+        normal: (point_1 - point_2).normalize_or_zero(),
+        distance: point_1.distance(point_2),
+    })
+}
+
+/// Expands the simplex GJK stopped with into a counter clockwise polygon inside the
+/// Minkowski difference that contains the origin, then grows it towards the boundary.
+/// Returns the outward normal of the closest boundary edge, its distance to the origin
+/// and the witness points on both cores.
+fn epa(shape_1: &Shape, shape_2: &Shape, mut polytope: Vec<SimplexVertex>) -> Option<(Vec2, f32, Vec2, Vec2)> {
+    // Touching cores leave GJK with a point or a segment, blow it up into a triangle
+    if polytope.len() == 1 {
+        let vertex = support(shape_1, shape_2, -polytope[0].point);
+        polytope.push(vertex);
+    }
+    if polytope.len() == 2 {
+        let edge = (polytope[1].point - polytope[0].point).perp();
+        let vertex = support(shape_1, shape_2, edge);
+        let vertex = if (vertex.point - polytope[0].point).dot(edge).abs() > TOLERANCE {
+            vertex
+        } else {
+            support(shape_1, shape_2, -edge)
+        };
+        polytope.push(vertex);
+    }
+    let area = (polytope[1].point - polytope[0].point).perp_dot(polytope[2].point - polytope[0].point);
+    if area.abs() <= TOLERANCE * TOLERANCE {
+        return None;
+    }
+    if area < 0. {
+        polytope.swap(1, 2);
+    }
+
+    for _ in 0..MAX_ITERATIONS {
+        let (index, normal, distance) = (0..polytope.len())
+            .filter_map(|i| {
+                let a = polytope[i].point;
+                let b = polytope[(i + 1) % polytope.len()].point;
+                // Counter clockwise, the outside is to the right of every edge
+                let normal = -(b - a).perp().normalize_or_zero();
+                (normal != Vec2::ZERO).then(|| (i, normal, normal.dot(a)))
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))?;
+
+        let vertex = support(shape_1, shape_2, normal);
+        if vertex.point.dot(normal) - distance <= TOLERANCE {
+            let a = polytope[index];
+            let b = polytope[(index + 1) % polytope.len()];
+            let edge = b.point - a.point;
+            let t = if edge.length_squared() > 0. {
+                ((normal * distance - a.point).dot(edge) / edge.length_squared()).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let point_1 = a.point_1.lerp(b.point_1, t);
+            let point_2 = a.point_2.lerp(b.point_2, t);
+            return Some((normal, distance, point_1, point_2));
+        }
+        polytope.insert(index + 1, vertex);
+    }
+    None
+}
+
+/// Closest points between the surfaces of two convex shapes, or `None` if they overlap.
+/// Shapes that only touch are zero apart.
+pub fn distance(
+    shape_1: &dyn SupportMap,
+    transform_1: &Transform,
+    shape_2: &dyn SupportMap,
+    transform_2: &Transform,
+) -> Option<ClosestPoints> {
+    let radius_1 = shape_1.radius();
+    let radius_2 = shape_2.radius();
+    let shape_1 = Shape { shape: shape_1, transform: transform_1 };
+    let shape_2 = Shape { shape: shape_2, transform: transform_2 };
+    let Gjk::Separated(closest) = gjk(&shape_1, &shape_2) else {
+        return None;
+    };
+    if closest.distance < radius_1 + radius_2 || closest.distance <= f32::EPSILON {
+        return None;
+    }
+    Some(ClosestPoints {
+        point_1: closest.point_1 - closest.normal * radius_1,
+        point_2: closest.point_2 + closest.normal * radius_2,
+        normal: closest.normal,
+        distance: closest.distance - radius_1 - radius_2,
+    })
+}
+
+/// Generic narrowphase for any pair of convex shapes, GJK while the cores are apart
+/// and EPA once they overlap. Follows the convention of [`CollisionData`], the normal
+/// points from the second shape to the first.
+pub fn collide(
+    shape_1: &dyn SupportMap,
+    transform_1: &Transform,
+    shape_2: &dyn SupportMap,
+    transform_2: &Transform,
+) -> Option<CollisionData> {
+    let radius_1 = shape_1.radius();
+    let radius_2 = shape_2.radius();
+    let shape_1 = Shape { shape: shape_1, transform: transform_1 };
+    let shape_2 = Shape { shape: shape_2, transform: transform_2 };
+
+    let (unit_normal, core_depth, point_1, point_2) = match gjk(&shape_1, &shape_2) {
+        Gjk::Separated(closest) => {
+            if closest.distance >= radius_1 + radius_2 || closest.distance <= f32::EPSILON {
+                return None;
+            }
+            (closest.normal, -closest.distance, closest.point_1, closest.point_2)
+        }
+        Gjk::Overlapping(simplex) => match epa(&shape_1, &shape_2, simplex) {
+            Some((normal, distance, point_1, point_2)) => (-normal, distance, point_1, point_2),
+            None => {
+                // Degenerate cores such as two crossing segments, push apart along the centres
+                let centers = (transform_1.translation - transform_2.translation).truncate();
+                let normal = if centers.length_squared() > 0. { centers.normalize() } else { Vec2::Y };
+                let point = (transform_1.translation + transform_2.translation).truncate() / 2.;
+                (normal, 0., point, point)
+            }
+        },
+    };
+
+    let penetration_depth = core_depth + radius_1 + radius_2;
+    if penetration_depth <= 0. {
+        return None;
+    }
+    let surface_1 = point_1 - unit_normal * radius_1;
+    let surface_2 = point_2 + unit_normal * radius_2;
+    Some(CollisionData {
+        unit_normal,
+        penetration_depth,
+        contact_point: (surface_1 + surface_2) / 2.,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32, y: f32, angle: f32) -> Transform {
+        Transform::from_xyz(x, y, 0.).with_rotation(Quat::from_rotation_z(angle))
+    }
+
+    fn assert_same(generic: Option<CollisionData>, specialised: Option<CollisionData>) {
+        match (generic, specialised) {
+            (Some(g), Some(s)) => {
+                assert!((g.penetration_depth - s.penetration_depth).abs() < 1e-3, "{g:?} {s:?}");
+                assert!(g.unit_normal.dot(s.unit_normal) > 0.999, "{g:?} {s:?}");
+            }
+            (None, None) => {}
+            (g, s) => panic!("{g:?} {s:?}"),
+        }
+    }
+
+    #[test]
+    fn collide_matches_specialised_routines() {
+        let circle = CircleCollider::new(8.);
+        let obb = OBB::new(20., 12.);
+        let capsule = Capsule::new(16., 5.);
+        let triangle = ConvexPolygon::new(vec![Vec2::new(-10., -6.), Vec2::new(10., -6.), Vec2::new(0., 12.)]).unwrap();
+        for i in 0..64 {
+            let angle = i as f32 * 0.4;
+            let t1 = at(3. * angle.cos() * (i % 7) as f32, 2. * angle.sin() * (i % 5) as f32, angle);
+            let t2 = at(1., -2., -0.3 * angle);
+            assert_same(collide(&circle, &t1, &circle, &t2), circle.is_colliding_with_circle(&t1, &circle, &t2));
+            assert_same(collide(&circle, &t1, &obb, &t2), circle.is_colliding_with_obb(&t1, &obb, &t2));
+            assert_same(collide(&circle, &t1, &triangle, &t2), circle.is_colliding_with_polygon(&t1, &triangle, &t2));
+            assert_same(collide(&circle, &t1, &capsule, &t2), circle.is_colliding_with_capsule(&t1, &capsule, &t2));
+            assert_same(collide(&capsule, &t1, &capsule, &t2), capsule.is_colliding_with_capsule(&t1, &capsule, &t2));
+        }
+    }
+
+    #[test]
+    fn collide_box_resting_on_box() {
+        let obb = OBB::new(20., 20.);
+        let contact = collide(&obb, &at(3., 19., 0.), &obb, &at(0., 0., 0.)).unwrap();
+        assert!((contact.unit_normal - Vec2::Y).length() < 1e-4, "{contact:?}");
+        assert!((contact.penetration_depth - 1.).abs() < 1e-4, "{contact:?}");
+        assert!(collide(&obb, &at(3., 21., 0.), &obb, &at(0., 0., 0.)).is_none());
+    }
+
+    #[test]
+    fn distance_between_separated_shapes() {
+        let circle = CircleCollider::new(5.);
+        let obb = OBB::new(10., 10.);
+        let closest = distance(&circle, &at(0., 20., 0.), &obb, &at(0., 0., 0.)).unwrap();
+        assert!((closest.distance - 10.).abs() < 1e-4, "{closest:?}");
+        assert!((closest.point_1 - Vec2::new(0., 15.)).length() < 1e-4, "{closest:?}");
+        assert!((closest.point_2 - Vec2::new(0., 5.)).length() < 1e-4, "{closest:?}");
+        assert!((closest.normal - Vec2::Y).length() < 1e-4, "{closest:?}");
+
+        let capsule = Capsule::new(16., 5.);
+        let closest = distance(&capsule, &at(30., 0., 0.), &circle, &at(0., 0., 0.)).unwrap();
+        // Upright so its side faces the circle
+        assert!((closest.distance - 20.).abs() < 1e-3, "{closest:?}");
+        assert!(distance(&circle, &at(0., 9., 0.), &obb, &at(0., 0., 0.)).is_none());
+
+        // Touching still tells which way apart is
+        let closest = distance(&circle, &at(10., 0., 0.), &obb, &at(0., 0., 0.)).unwrap();
+        assert_eq!(closest.distance, 0.);
+        assert!((closest.normal - Vec2::X).length() < 1e-4, "{closest:?}");
+    }
+}
@@ -0,0 +1,379 @@
+pub mod aabb_tree;
+pub mod broadphase;
+pub mod ccd;
+pub mod collision_primitives;
+pub mod events;
+pub mod gjk;
+pub mod rigidbody;
+pub mod solver;
+pub mod spatial_query;
+
+use bevy::{prelude::*, sprite::{Mesh2dHandle, Material2d}};
+use broadphase::{BroadphaseProxy, PhysicsBroadphase};
+use events::{CollidingPairs, CollisionEnded, CollisionEventWriters, CollisionStarted, Contact};
+use collision_primitives::{ContactManifold, Primitives, CollisionData};
+use rigidbody::{ContinuousCollision, GravityScale, Rigidbody, Mass, Sensor};
+use solver::{ContactCache, ContactConstraint, ContactId, SolverBody};
+
+/// Registers the physics resources and systems.
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhysicsConfig>()
+            .init_resource::<PhysicsAccumulator>()
+            .init_resource::<SolverConfig>()
+            .init_resource::<ContactCache>()
+            .init_resource::<PhysicsBroadphase>()
+            .init_resource::<Gravity>()
+            .init_resource::<CollidingPairs>()
+            .add_event::<CollisionStarted>()
+            .add_event::<CollisionEnded>()
+            .add_event::<Contact>()
+            .add_system(step_physics);
+    }
+}
+
+#[derive(Bundle)]
+pub struct RigidbodyBundle<M: Material2d> {
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub mesh: Mesh2dHandle,
+    pub material: Handle<M>,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+    pub rigidbody: Rigidbody
+}
+
+/// Fixed timestep settings for the simulation.
+///
+/// Insert this resource before adding [`PhysicsPlugin`] to override the defaults.
+#[derive(Resource, Debug, Clone)]
+pub struct PhysicsConfig {
+    /// Length of one fixed step in seconds.
+    pub timestep: f32,
+    /// Number of substeps each fixed step is split into.
+    pub substeps: u32,
+    /// Upper bound on fixed steps run in a single frame. Stops a slow frame from
+    /// queueing ever more steps (the "spiral of death"); leftover time is dropped.
+    pub max_steps_per_frame: u32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            timestep: 1. / 60.,
+            substeps: 1,
+            max_steps_per_frame: 5,
+        }
+    }
+}
+
+impl PhysicsConfig {
+    /// Length of a single substep in seconds.
+    pub fn substep_dt(&self) -> f32 {
+        self.timestep / self.substeps.max(1) as f32
+    }
+}
+
+/// Settings for the collision solver.
+#[derive(Resource, Debug, Clone)]
+pub struct SolverConfig {
+    /// Number of passes the sequential impulse solver makes over all contacts each step.
+    /// More iterations make stacks stiffer at the cost of performance.
+    pub velocity_iterations: u32,
+    /// Seeds each contact with the impulse it ended the previous step with.
+    pub warm_starting: bool,
+    /// Closing speed below which contacts do not bounce.
+    pub restitution_threshold: f32,
+    /// Penetration depth that is tolerated without positional correction. Keeps resting
+    /// contacts touching so they do not jitter in and out of collision.
+    pub penetration_slop: f32,
+    /// Fraction of the remaining penetration removed each step, between `0.` and `1.`.
+    pub correction_percent: f32,
+    /// Also creates contacts for pairs that are not touching yet but could within the next
+    /// step given their velocities. The solver only lets them close the gap, so fast bodies
+    /// stop at the surface instead of passing through it. Cheaper than
+    /// [`ContinuousCollision`] and applies to every body, but ignores rotation.
+    pub speculative_contacts: bool,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            velocity_iterations: 8,
+            warm_starting: true,
+            restitution_threshold: 100.,
+            penetration_slop: 0.5,
+            correction_percent: 0.4,
+            speculative_contacts: false,
+        }
+    }
+}
+
+/// World gravity, applied as an acceleration to every dynamic body.
+///
+/// Scale it per body with a [`GravityScale`] component.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Gravity(pub Vec2);
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Self(Vec2::new(0., -981.))
+    }
+}
+
+/// Frame time that has not yet been consumed by a fixed step.
+#[derive(Resource, Debug, Default)]
+pub struct PhysicsAccumulator(pub f32);
+
+type RigidbodyQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Rigidbody,
+        &'static mut Transform,
+        Option<&'static GravityScale>,
+        Option<&'static ContinuousCollision>,
+        Option<&'static Sensor>,
+    ),
+>;
+
+#[allow(clippy::too_many_arguments)]
+pub fn step_physics(
+    mut rigidbodies: RigidbodyQuery,
+    time: Res<Time>,
+    config: Res<PhysicsConfig>,
+    gravity: Res<Gravity>,
+    solver_config: Res<SolverConfig>,
+    mut contact_cache: ResMut<ContactCache>,
+    mut broadphase: ResMut<PhysicsBroadphase>,
+    mut accumulator: ResMut<PhysicsAccumulator>,
+    mut colliding_pairs: ResMut<CollidingPairs>,
+    collision_started: EventWriter<CollisionStarted>,
+    collision_ended: EventWriter<CollisionEnded>,
+    contact_events: EventWriter<Contact>) {
+    accumulator.0 += time.delta_seconds();
+
+    let mut events = CollisionEventWriters {
+        pairs: &mut colliding_pairs,
+        started: collision_started,
+        ended: collision_ended,
+        contacts: contact_events,
+    };
+    let dt = config.substep_dt();
+    let mut steps = 0;
+    while accumulator.0 >= config.timestep {
+        if steps >= config.max_steps_per_frame {
+            // Too far behind to catch up, drop whole steps but keep the remainder
+            accumulator.0 %= config.timestep;
+            break;
+        }
+        for _ in 0..config.substeps.max(1) {
+            simulate(&mut rigidbodies, gravity.0, &solver_config, &mut contact_cache, &mut broadphase, &mut events, dt);
+        }
+        accumulator.0 -= config.timestep;
+        steps += 1;
+    }
+
+    if steps > 0 {
+        rigidbodies.for_each_mut(|(_, mut r, ..)| r.clear_forces());
+    }
+}
+
+/// Advances every rigidbody by `dt` seconds and resolves the resulting collisions.
+fn simulate(
+    rigidbodies: &mut RigidbodyQuery,
+    gravity: Vec2,
+    solver_config: &SolverConfig,
+    contact_cache: &mut ContactCache,
+    broadphase: &mut PhysicsBroadphase,
+    events: &mut CollisionEventWriters,
+    dt: f32,
+) {
+    rigidbodies.for_each_mut(|(_, mut r, _, gravity_scale, ..)| {
+        // Update Velocities
+        if let Mass::Some(m) = r.mass {
+            // Apply forces
+            let scale = gravity_scale.map_or(1., |g| g.0);
+            let linear_acceleration = r.force / m + gravity * scale;
+            r.linear_velocity += linear_acceleration * dt;
+
+            let angular_acceleration = r.torque * r.inverse_moment();
+            r.angular_velocity += angular_acceleration * dt;
+        }
+        // If object is static, no kinematics need to take place
+    });
+
+    // Gather every contact before solving any of them
+    let colliders: Vec<_> = rigidbodies.iter().map(|(e, r, t, ..)| (e, r, t)).collect();
+    let continuous: Vec<bool> = rigidbodies.iter().map(|(.., ccd, _)| ccd.is_some()).collect();
+    let sensors: Vec<bool> = rigidbodies.iter().map(|(.., sensor)| sensor.is_some()).collect();
+    let mut bodies: Vec<SolverBody> = colliders
+        .iter()
+        .map(|(e, r, t)| SolverBody::new(*e, r, t))
+        .collect();
+    let proxies: Vec<BroadphaseProxy> = colliders
+        .iter()
+        .zip(&bodies)
+        .map(|((e, r, t), body)| {
+            let (mut min, mut max) = r.shape.get_world_bounds(t);
+            if solver_config.speculative_contacts {
+                // Cover everything the body could reach this step
+                let motion = body.linear_velocity * dt;
+                (min, max) = (min.min(min + motion), max.max(max + motion));
+            }
+            BroadphaseProxy { entity: *e, min, max, is_static: body.inverse_mass <= 0. }
+        })
+        .collect();
+    let mut contacts = Vec::new();
+    let mut sensor_pairs = Vec::new();
+    for (i, j) in broadphase.0.find_pairs(&proxies) {
+        let (entity_1, rigidbody_1, transform_1) = &colliders[i];
+        let (entity_2, rigidbody_2, transform_2) = &colliders[j];
+        if sensors[i] || sensors[j] {
+            // Only reported, never solved
+            if !collide_shapes(&rigidbody_1.shape, transform_1, &rigidbody_2.shape, transform_2, 0.).is_empty() {
+                sensor_pairs.push((*entity_1, *entity_2));
+            }
+            continue;
+        }
+        let speculative_margin = if solver_config.speculative_contacts {
+            (bodies[i].linear_velocity - bodies[j].linear_velocity).length() * dt
+        } else {
+            0.
+        };
+        for (child_1, child_2, manifold) in collide_shapes(&rigidbody_1.shape, transform_1, &rigidbody_2.shape, transform_2, speculative_margin) {
+            for point in &manifold.points {
+                let id = ContactId { entity_1: *entity_1, entity_2: *entity_2, child_1, child_2, feature: point.feature };
+                contacts.push(ContactConstraint::new(id, i, j, manifold.unit_normal, point, rigidbody_1, rigidbody_2));
+            }
+        }
+    }
+
+    // Solve velocities first so positions are only ever integrated with velocities
+    // that respect the contacts
+    solver::solve_contacts(&mut bodies, &mut contacts, solver_config, contact_cache, dt);
+    let start_positions: Vec<Vec2> = bodies.iter().map(|b| b.position).collect();
+    solver::integrate_positions(&mut bodies, dt);
+    ccd::solve_time_of_impacts(&mut bodies, &start_positions, &colliders, &continuous, &sensors);
+    solver::correct_positions(&mut bodies, &contacts, solver_config);
+    events.send(&contacts, &sensor_pairs);
+
+    for body in bodies {
+        if let Ok((_, mut r, mut t, ..)) = rigidbodies.get_mut(body.entity) {
+            r.linear_velocity = body.linear_velocity;
+            r.angular_velocity = body.angular_velocity;
+            t.translation = body.position.extend(t.translation.z);
+            t.rotation = body.rotation;
+        }
+    }
+
+    // Spatial queries run between steps and need the bounds the bodies ended up with
+    let final_proxies: Vec<BroadphaseProxy> = proxies
+        .iter()
+        .filter_map(|proxy| {
+            let (_, r, t, ..) = rigidbodies.get(proxy.entity).ok()?;
+            let (min, max) = r.shape.get_world_bounds(t);
+            Some(BroadphaseProxy { min, max, ..*proxy })
+        })
+        .collect();
+    broadphase.0.update_bounds(&final_proxies);
+}
+
+/// Narrowphase between two bodies, with one manifold per pair of touching children. The
+/// indices are those of the children of a [`Compound`](collision_primitives::Compound) and zero for any other shape.
+/// Children closer than `speculative_margin` get a speculative contact. Every point is
+/// anchored to the bodies at `transform_1` and `transform_2`.
+fn collide_shapes(shape_1: &Primitives, transform_1: &Transform, shape_2: &Primitives, transform_2: &Transform, speculative_margin: f32) -> Vec<(usize, usize, ContactManifold)> {
+    let mut manifolds = collide_parts(shape_1, transform_1, shape_2, transform_2, speculative_margin);
+    for (.., manifold) in &mut manifolds {
+        manifold.set_local_anchors(transform_1, transform_2);
+    }
+    manifolds
+}
+
+/// [`collide_shapes`] without the anchors, recursing into the children of compounds.
+fn collide_parts(shape_1: &Primitives, transform_1: &Transform, shape_2: &Primitives, transform_2: &Transform, speculative_margin: f32) -> Vec<(usize, usize, ContactManifold)> {
+    match (shape_1, shape_2) {
+        (Primitives::Compound(compound), _) => compound
+            .children()
+            .iter()
+            .enumerate()
+            .flat_map(|(i, child)| {
+                let child_transform = child.get_world_transform(transform_1);
+                collide_parts(&child.shape, &child_transform, shape_2, transform_2, speculative_margin)
+                    .into_iter()
+                    .map(move |(_, j, manifold)| (i, j, manifold))
+            })
+            .collect(),
+        (_, Primitives::Compound(compound)) => compound
+            .children()
+            .iter()
+            .enumerate()
+            .flat_map(|(j, child)| {
+                let child_transform = child.get_world_transform(transform_2);
+                collide_parts(shape_1, transform_1, &child.shape, &child_transform, speculative_margin)
+                    .into_iter()
+                    .map(move |(i, _, manifold)| (i, j, manifold))
+            })
+            .collect(),
+        _ => contact_manifold(shape_1, transform_1, shape_2, transform_2)
+            .or_else(|| {
+                let contact = ccd::speculative_contact(shape_1, transform_1, shape_2, transform_2, speculative_margin)?;
+                Some(ContactManifold::from(contact))
+            })
+            .map(|manifold| (0, 0, manifold))
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Polygon pairs are clipped into manifolds with up to two points, every other pair
+/// gets a single point from its narrowphase test.
+fn contact_manifold(shape_1: &Primitives, transform_1: &Transform, shape_2: &Primitives, transform_2: &Transform) -> Option<ContactManifold> {
+    match (shape_1.get_world_polygon(transform_1), shape_2.get_world_polygon(transform_2)) {
+        // Neither has any area, they are only meant for static geometry
+        (Some((verts_1, 0.)), Some((verts_2, 0.))) if verts_1.len() == 2 && verts_2.len() == 2 => None,
+        (Some((verts_1, radius_1)), Some((verts_2, radius_2))) if radius_1 == 0. && radius_2 == 0. => {
+            collision_primitives::clip_convex(&verts_1, 0., &verts_2, 0.)
+        }
+        (Some((verts_1, radius_1)), Some((verts_2, radius_2))) => {
+            // Rounded, the clipped face only holds if the narrowphase agrees with its normal
+            let contact = check_collision(shape_1, transform_1, shape_2, transform_2)?;
+            match collision_primitives::clip_convex(&verts_1, radius_1, &verts_2, radius_2) {
+                Some(manifold) if manifold.unit_normal.dot(contact.unit_normal) > 0.999 => Some(manifold),
+                _ => Some(contact.into()),
+            }
+        }
+        _ => check_collision(shape_1, transform_1, shape_2, transform_2).map(ContactManifold::from),
+    }
+}
+
+/// Single point narrowphase. Pairs with a specialised routine use it, every other pair
+/// of convex shapes goes through the generic [`gjk::collide`].
+fn check_collision(shape_1: &Primitives, transform_1: &Transform, shape_2: &Primitives, transform_2: &Transform) -> Option<CollisionData> {
+    match (shape_1, shape_2) {
+        // Split into their children by collide_parts
+        (Primitives::Compound(_), _) | (_, Primitives::Compound(_)) => None,
+        // Neither has any area, they are only meant for static geometry
+        (Primitives::Segment(_) | Primitives::Polyline(_), Primitives::Segment(_) | Primitives::Polyline(_)) => None,
+        (Primitives::Circle(c1), Primitives::Circle(c2)) => c1.is_colliding_with_circle(transform_1, c2, transform_2),
+        (Primitives::Circle(c1), Primitives::AABB(c2)) => c1.is_colliding_with_aabb(transform_1, c2, transform_2),
+        (Primitives::AABB(c1), Primitives::Circle(c2)) => c1.is_colliding_with_circle(transform_1, c2, transform_2),
+        (Primitives::Circle(c1), Primitives::OBB(c2)) => c1.is_colliding_with_obb(transform_1, c2, transform_2),
+        (Primitives::OBB(c1), Primitives::Circle(c2)) => c1.is_colliding_with_circle(transform_1, c2, transform_2),
+        (Primitives::Circle(c1), Primitives::ConvexPolygon(c2)) => c1.is_colliding_with_polygon(transform_1, c2, transform_2),
+        (Primitives::ConvexPolygon(c1), Primitives::Circle(c2)) => c1.is_colliding_with_circle(transform_1, c2, transform_2),
+        (Primitives::Circle(c1), Primitives::Capsule(c2)) => c1.is_colliding_with_capsule(transform_1, c2, transform_2),
+        (Primitives::Capsule(c1), Primitives::Circle(c2)) => c1.is_colliding_with_circle(transform_1, c2, transform_2),
+        (Primitives::Capsule(c1), Primitives::Capsule(c2)) => c1.is_colliding_with_capsule(transform_1, c2, transform_2),
+        // Not convex, tested one segment at a time
+        (Primitives::Polyline(c1), _) => c1.is_colliding_with_convex(transform_1, shape_2.as_support_map()?, transform_2),
+        (_, Primitives::Polyline(c2)) => c2
+            .is_colliding_with_convex(transform_2, shape_1.as_support_map()?, transform_1)
+            .map(CollisionData::flipped),
+        _ => gjk::collide(shape_1.as_support_map()?, transform_1, shape_2.as_support_map()?, transform_2),
+    }
+}
@@ -1,6 +1,6 @@
 use bevy::prelude::{Vec2, *};
 
-use crate::collision_primitives::{Primitives};
+use crate::collision_primitives::{Compound, Primitives};
 
 #[derive(Component)]
 pub struct Rigidbody {
@@ -10,10 +10,37 @@ pub struct Rigidbody {
     pub angular_velocity: f32,
     pub torque: f32,
     pub restitution: Restitution,
+    pub friction: Friction,
     pub mass: Mass,
     pub moment: f32,
 }
 
+/// Multiplier on the world [`Gravity`](crate::Gravity) for a single body.
+///
+/// `0.` makes a body float, negative values make it rise.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct GravityScale(pub f32);
+
+impl Default for GravityScale {
+    fn default() -> Self {Self(1.)}
+}
+
+/// Opts a body into continuous collision detection.
+///
+/// A body moving further than half its size in a single step can pass straight
+/// through thin geometry. With this component it is stopped at the first impact of the
+/// step instead. Meant for bullets and other small fast bodies, it costs a sweep
+/// against every body near its path each step.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct ContinuousCollision;
+
+/// Makes a body a trigger: its overlaps are still detected and reported through
+/// [`CollisionStarted`](crate::events::CollisionStarted) and
+/// [`CollisionEnded`](crate::events::CollisionEnded), but the solver ignores them and
+/// nothing is pushed apart. For pickups, kill zones, checkpoints and the like.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Sensor;
+
 pub enum Mass {
     Static,
     Some(f32),
@@ -28,13 +55,16 @@ impl Rigidbody {
             angular_velocity: 0.,
             torque: 0.,
             restitution: Restitution::new(0.8),
+            friction: Friction::new(0.5, 0.3),
             mass: Mass::Static,
             moment: 0.
         }
     }
 
-    pub fn new_shape_with_velocity(mass: f32, radius: f32, primitive: Primitives, velocity: Vec2) -> Self {
-        let I = 0.5 * mass * radius.powf(2.);
+    /// Dynamic body whose moment of inertia is that of `mass` spread evenly over the shape,
+    /// see [`Primitives::moment_of_inertia`].
+    pub fn new_shape_with_velocity(mass: f32, primitive: Primitives, velocity: Vec2) -> Self {
+        let moment = primitive.moment_of_inertia(mass);
         Self {
             shape: primitive,
             linear_velocity: velocity,
@@ -42,10 +72,104 @@ impl Rigidbody {
             angular_velocity: 0.,
             torque: 0.,
             restitution: Restitution::new(0.8),
+            friction: Friction::new(0.5, 0.3),
+            mass: Mass::Some(mass),
+            moment
+        }
+    }
+
+    /// Dynamic body made of several shapes. Mass and moment of inertia are those of all
+    /// the children combined.
+    pub fn new_compound_with_velocity(compound: Compound, velocity: Vec2) -> Self {
+        let mass = compound.mass();
+        let moment = compound.moment_of_inertia();
+        Self {
+            shape: Primitives::Compound(compound),
+            linear_velocity: velocity,
+            force: Vec2::new(0.,0.),
+            angular_velocity: 0.,
+            torque: 0.,
+            restitution: Restitution::new(0.8),
+            friction: Friction::new(0.5, 0.3),
             mass: Mass::Some(mass),
-            moment: I
+            moment
         }
     }
+
+    /// `1 / mass`, or `0.` for static bodies.
+    pub fn inverse_mass(&self) -> f32 {
+        match self.mass {
+            Mass::Some(m) => 1. / m,
+            Mass::Static => 0.,
+        }
+    }
+
+    /// `1 / moment`, or `0.` for static bodies, bodies without a moment of inertia and
+    /// bodies that can't rotate.
+    pub fn inverse_moment(&self) -> f32 {
+        match self.mass {
+            Mass::Some(_) if self.moment > 0. && self.can_rotate() => 1. / self.moment,
+            _ => 0.,
+        }
+    }
+
+    /// Whether the solver lets this body rotate. [`AABB`](crate::collision_primitives::AABB)
+    /// shapes stay axis aligned whatever their transform says, so bodies using one never
+    /// rotate: contacts and torques leave their angular velocity untouched and any spin
+    /// set on them is ignored. Use an [`OBB`](crate::collision_primitives::OBB) for a box
+    /// that should tumble.
+    pub fn can_rotate(&self) -> bool {
+        !matches!(self.shape, Primitives::AABB(_))
+    }
+
+    /// Adds a force through the centre of mass.
+    ///
+    /// Forces accumulate until the next frame that runs at least one physics step.
+    /// They act over every step of that frame and are then cleared.
+    pub fn apply_force(&mut self, force: Vec2) {
+        self.force += force;
+    }
+
+    /// Adds a force acting at a world space `point`, producing torque about the
+    /// body's centre as well as a linear force.
+    pub fn apply_force_at_point(&mut self, force: Vec2, point: Vec2, transform: &Transform) {
+        let lever_arm = point - transform.translation.truncate();
+        self.force += force;
+        self.torque += lever_arm.perp_dot(force);
+    }
+
+    /// Adds a torque about the centre of mass. Accumulates like [`Rigidbody::apply_force`].
+    pub fn apply_torque(&mut self, torque: f32) {
+        self.torque += torque;
+    }
+
+    /// Immediately changes the linear velocity by `impulse / mass`. Does nothing to static bodies.
+    pub fn apply_impulse(&mut self, impulse: Vec2) {
+        self.linear_velocity += impulse * self.inverse_mass();
+    }
+
+    /// Immediately changes the angular velocity by `impulse / moment`. Does nothing to static bodies.
+    pub fn apply_angular_impulse(&mut self, impulse: f32) {
+        self.angular_velocity += impulse * self.inverse_moment();
+    }
+
+    /// Immediately applies `impulse` at `lever_arm` from the centre of mass, changing both
+    /// linear and angular velocity.
+    pub fn apply_impulse_at(&mut self, impulse: Vec2, lever_arm: Vec2) {
+        self.apply_impulse(impulse);
+        self.apply_angular_impulse(lever_arm.perp_dot(impulse));
+    }
+
+    /// Velocity of the point at `lever_arm` from the centre of mass, including rotation.
+    pub fn velocity_at(&self, lever_arm: Vec2) -> Vec2 {
+        self.linear_velocity + lever_arm.perp() * self.angular_velocity
+    }
+
+    /// Resets the force and torque accumulators.
+    pub fn clear_forces(&mut self) {
+        self.force = Vec2::ZERO;
+        self.torque = 0.;
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -73,4 +197,39 @@ impl Restitution {
 
 impl Default for Restitution {
     fn default() -> Self {Self {r: 1.}}
-}
\ No newline at end of file
+}
+
+/// Coulomb friction coefficients.
+///
+/// `static_coefficient` limits the tangential impulse that can hold a contact still,
+/// `dynamic_coefficient` scales the impulse once the contact is sliding.
+#[derive(Debug, Clone)]
+pub struct Friction {
+    static_coefficient: f32,
+    dynamic_coefficient: f32,
+}
+
+impl Friction {
+    /// Negative coefficients are clamped to `0.` and the dynamic coefficient is clamped
+    /// to never exceed the static one.
+    pub fn new(static_coefficient: f32, dynamic_coefficient: f32) -> Self {
+        let static_coefficient = static_coefficient.max(0.);
+        Self {
+            static_coefficient,
+            dynamic_coefficient: dynamic_coefficient.clamp(0., static_coefficient),
+        }
+    }
+
+    /// Combines the coefficients of two bodies by taking the smaller of each, like
+    /// [`Restitution::resolve_restitutions`], returning `(static, dynamic)`.
+    pub fn resolve_frictions(&self, other: &Friction) -> (f32, f32) {
+        (
+            self.static_coefficient.min(other.static_coefficient),
+            self.dynamic_coefficient.min(other.dynamic_coefficient),
+        )
+    }
+}
+
+impl Default for Friction {
+    fn default() -> Self {Self {static_coefficient: 0., dynamic_coefficient: 0.}}
+}
@@ -0,0 +1,318 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    collision_primitives::{CollisionData, ManifoldPoint},
+    rigidbody::Rigidbody,
+    SolverConfig,
+};
+
+/// Identifies a contact across steps so its impulses can be reused for warm starting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContactId {
+    pub entity_1: Entity,
+    pub entity_2: Entity,
+    /// Index of the child of a [`Compound`](crate::collision_primitives::Compound) hit on
+    /// each body, zero for any other shape.
+    pub child_1: usize,
+    pub child_2: usize,
+    /// Distinguishes contacts between the same pair of bodies.
+    pub feature: u32,
+}
+
+/// Impulses accumulated by each contact during the last step, keyed by [`ContactId`].
+#[derive(Resource, Debug, Default)]
+pub struct ContactCache(pub HashMap<ContactId, AccumulatedImpulse>);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AccumulatedImpulse {
+    pub normal: f32,
+    pub tangent: f32,
+}
+
+/// Copy of the state of a rigidbody the solver reads and writes.
+pub(crate) struct SolverBody {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub rotation: Quat,
+    pub linear_velocity: Vec2,
+    pub angular_velocity: f32,
+    pub inverse_mass: f32,
+    pub inverse_moment: f32,
+}
+
+impl SolverBody {
+    pub fn new(entity: Entity, rigidbody: &Rigidbody, transform: &Transform) -> Self {
+        Self {
+            entity,
+            position: transform.translation.truncate(),
+            rotation: transform.rotation,
+            linear_velocity: rigidbody.linear_velocity,
+            angular_velocity: if rigidbody.can_rotate() { rigidbody.angular_velocity } else { 0. },
+            inverse_mass: rigidbody.inverse_mass(),
+            inverse_moment: rigidbody.inverse_moment(),
+        }
+    }
+
+    /// World space offset from the body's centre to a point anchored at `local_anchor`.
+    fn lever_arm(&self, local_anchor: Vec2) -> Vec2 {
+        (self.rotation * local_anchor.extend(0.)).truncate()
+    }
+
+    fn velocity_at(&self, lever_arm: Vec2) -> Vec2 {
+        self.linear_velocity + lever_arm.perp() * self.angular_velocity
+    }
+
+    fn apply_impulse_at(&mut self, impulse: Vec2, lever_arm: Vec2) {
+        self.linear_velocity += impulse * self.inverse_mass;
+        self.angular_velocity += lever_arm.perp_dot(impulse) * self.inverse_moment;
+    }
+}
+
+/// A single contact point between the bodies at `body_1` and `body_2`.
+pub(crate) struct ContactConstraint {
+    pub id: ContactId,
+    pub body_1: usize,
+    pub body_2: usize,
+    pub collision_data: CollisionData,
+    /// The contact point in the local space of each body, follows the bodies as they move.
+    local_anchor_1: Vec2,
+    local_anchor_2: Vec2,
+    r_1: Vec2,
+    r_2: Vec2,
+    tangent: Vec2,
+    normal_mass: f32,
+    tangent_mass: f32,
+    restitution: f32,
+    static_friction: f32,
+    dynamic_friction: f32,
+    velocity_bias: f32,
+    /// Relative velocity along the normal before this step, bounced off by
+    /// speculative contacts once the solver is done.
+    approach_velocity: f32,
+    impulse: AccumulatedImpulse,
+}
+
+impl ContactConstraint {
+    pub fn new(
+        id: ContactId,
+        body_1: usize,
+        body_2: usize,
+        unit_normal: Vec2,
+        point: &ManifoldPoint,
+        rigidbody_1: &Rigidbody,
+        rigidbody_2: &Rigidbody,
+    ) -> Self {
+        let (static_friction, dynamic_friction) = rigidbody_1.friction.resolve_frictions(&rigidbody_2.friction);
+        Self {
+            id,
+            body_1,
+            body_2,
+            tangent: unit_normal.perp(),
+            collision_data: CollisionData {
+                unit_normal,
+                penetration_depth: point.penetration_depth,
+                contact_point: point.point,
+            },
+            local_anchor_1: point.local_anchor_1,
+            local_anchor_2: point.local_anchor_2,
+            r_1: Vec2::ZERO,
+            r_2: Vec2::ZERO,
+            normal_mass: 0.,
+            tangent_mass: 0.,
+            restitution: rigidbody_1.restitution.resolve_restitutions(&rigidbody_2.restitution),
+            static_friction,
+            dynamic_friction,
+            velocity_bias: 0.,
+            approach_velocity: 0.,
+            impulse: AccumulatedImpulse::default(),
+        }
+    }
+
+    /// Computes lever arms, effective masses and the restitution target from the
+    /// velocities before any impulse of this step is applied.
+    fn prepare(&mut self, bodies: &[SolverBody], restitution_threshold: f32, dt: f32) {
+        let body_1 = &bodies[self.body_1];
+        let body_2 = &bodies[self.body_2];
+        let normal = self.collision_data.unit_normal;
+        self.r_1 = body_1.lever_arm(self.local_anchor_1);
+        self.r_2 = body_2.lever_arm(self.local_anchor_2);
+
+        let effective_mass = |axis: Vec2| {
+            let r_1_cross = self.r_1.perp_dot(axis);
+            let r_2_cross = self.r_2.perp_dot(axis);
+            body_1.inverse_mass
+                + body_2.inverse_mass
+                + r_1_cross * r_1_cross * body_1.inverse_moment
+                + r_2_cross * r_2_cross * body_2.inverse_moment
+        };
+        let k_normal = effective_mass(normal);
+        let k_tangent = effective_mass(self.tangent);
+        self.normal_mass = if k_normal > 0. { 1. / k_normal } else { 0. };
+        self.tangent_mass = if k_tangent > 0. { 1. / k_tangent } else { 0. };
+
+        // Only bounce on impacts, resting contacts would otherwise never settle
+        let relative_velocity = body_1.velocity_at(self.r_1) - body_2.velocity_at(self.r_2);
+        let velocity_along_normal = relative_velocity.dot(normal);
+        self.approach_velocity = velocity_along_normal;
+        self.velocity_bias = if self.collision_data.penetration_depth < 0. {
+            // Speculative, the bodies may still close the gap this step but no further
+            self.collision_data.penetration_depth / dt
+        } else if velocity_along_normal < -restitution_threshold {
+            -self.restitution * velocity_along_normal
+        } else {
+            0.
+        };
+    }
+
+    /// Impulse accumulated on the first body this step, normal and friction together.
+    pub fn applied_impulse(&self) -> Vec2 {
+        self.collision_data.unit_normal * self.impulse.normal + self.tangent * self.impulse.tangent
+    }
+
+    fn apply(&self, bodies: &mut [SolverBody], impulse: Vec2) {
+        bodies[self.body_1].apply_impulse_at(impulse, self.r_1);
+        bodies[self.body_2].apply_impulse_at(-impulse, self.r_2);
+    }
+
+    fn relative_velocity(&self, bodies: &[SolverBody]) -> Vec2 {
+        bodies[self.body_1].velocity_at(self.r_1) - bodies[self.body_2].velocity_at(self.r_2)
+    }
+
+    fn solve_velocity(&mut self, bodies: &mut [SolverBody]) {
+        let normal = self.collision_data.unit_normal;
+
+        // Friction first, clamped to the cone given by the current normal impulse.
+        // Sticks while inside the static cone, otherwise slides with dynamic friction.
+        let velocity_along_tangent = self.relative_velocity(bodies).dot(self.tangent);
+        let lambda = -velocity_along_tangent * self.tangent_mass;
+        let mut tangent_impulse = self.impulse.tangent + lambda;
+        let max_static = self.static_friction * self.impulse.normal;
+        if tangent_impulse.abs() > max_static {
+            let max_dynamic = self.dynamic_friction * self.impulse.normal;
+            tangent_impulse = tangent_impulse.clamp(-max_dynamic, max_dynamic);
+        }
+        let lambda = tangent_impulse - self.impulse.tangent;
+        self.impulse.tangent = tangent_impulse;
+        self.apply(bodies, self.tangent * lambda);
+
+        // Normal impulse, the accumulated total may never pull the bodies together
+        let velocity_along_normal = self.relative_velocity(bodies).dot(normal);
+        let lambda = (self.velocity_bias - velocity_along_normal) * self.normal_mass;
+        let normal_impulse = (self.impulse.normal + lambda).max(0.);
+        let lambda = normal_impulse - self.impulse.normal;
+        self.impulse.normal = normal_impulse;
+        self.apply(bodies, normal * lambda);
+    }
+
+    /// Bounces a speculative contact that ended up stopping the bodies. Its bias only lets
+    /// them close the gap, so restitution has to be applied after the fact.
+    ///
+    /// Contacts too inelastic to bounce faster than `restitution_threshold` are left to
+    /// close the gap, the bodies then meet and settle on the regular contact of the next step.
+    fn apply_restitution(&mut self, bodies: &mut [SolverBody], restitution_threshold: f32) {
+        if self.collision_data.penetration_depth >= 0.
+            || self.impulse.normal <= 0.
+            || -self.restitution * self.approach_velocity <= restitution_threshold
+        {
+            return;
+        }
+        let normal = self.collision_data.unit_normal;
+        let velocity_along_normal = self.relative_velocity(bodies).dot(normal);
+        let lambda = (-self.restitution * self.approach_velocity - velocity_along_normal) * self.normal_mass;
+        let normal_impulse = (self.impulse.normal + lambda).max(0.);
+        let lambda = normal_impulse - self.impulse.normal;
+        self.impulse.normal = normal_impulse;
+        self.apply(bodies, normal * lambda);
+    }
+
+    /// Penetration left once the bodies moved, measured between the anchored points so
+    /// bodies that already separated along the normal are not pushed apart again.
+    fn current_depth(&self, bodies: &[SolverBody]) -> f32 {
+        let body_1 = &bodies[self.body_1];
+        let body_2 = &bodies[self.body_2];
+        let point_1 = body_1.position + body_1.lever_arm(self.local_anchor_1);
+        let point_2 = body_2.position + body_2.lever_arm(self.local_anchor_2);
+        self.collision_data.penetration_depth - (point_1 - point_2).dot(self.collision_data.unit_normal)
+    }
+
+    /// Pushes the bodies apart along the contact normal, split by inverse mass.
+    ///
+    /// Only the penetration beyond `penetration_slop` is corrected, and only
+    /// `correction_percent` of it, so resting contacts settle instead of jittering.
+    /// `share` is the part of that correction this contact is responsible for.
+    fn correct_positions(&self, bodies: &mut [SolverBody], solver_config: &SolverConfig, share: f32) {
+        let invert_mass_1 = bodies[self.body_1].inverse_mass;
+        let invert_mass_2 = bodies[self.body_2].inverse_mass;
+        let invert_mass_sum = invert_mass_1 + invert_mass_2;
+        if invert_mass_sum <= 0. {
+            return
+        }
+        let depth = (self.current_depth(bodies) - solver_config.penetration_slop).max(0.);
+        let correction = self.collision_data.unit_normal * depth * solver_config.correction_percent * share / invert_mass_sum;
+        bodies[self.body_1].position += correction * invert_mass_1;
+        bodies[self.body_2].position -= correction * invert_mass_2;
+    }
+}
+
+/// Sequential impulse solver.
+///
+/// Every contact is solved `velocity_iterations` times with accumulated impulse clamping,
+/// optionally warm started from the impulses stored in `cache` by the previous step.
+/// The cache is replaced with the impulses of this step.
+pub(crate) fn solve_contacts(
+    bodies: &mut [SolverBody],
+    contacts: &mut [ContactConstraint],
+    solver_config: &SolverConfig,
+    cache: &mut ContactCache,
+    dt: f32,
+) {
+    for contact in contacts.iter_mut() {
+        contact.prepare(bodies, solver_config.restitution_threshold, dt);
+    }
+
+    if solver_config.warm_starting {
+        for contact in contacts.iter_mut() {
+            if let Some(impulse) = cache.0.get(&contact.id) {
+                contact.impulse = *impulse;
+                let total = contact.collision_data.unit_normal * impulse.normal + contact.tangent * impulse.tangent;
+                contact.apply(bodies, total);
+            }
+        }
+    }
+
+    for _ in 0..solver_config.velocity_iterations {
+        for contact in contacts.iter_mut() {
+            contact.solve_velocity(bodies);
+        }
+    }
+    for contact in contacts.iter_mut() {
+        contact.apply_restitution(bodies, solver_config.restitution_threshold);
+    }
+
+    cache.0.clear();
+    cache.0.extend(contacts.iter().map(|c| (c.id, c.impulse)));
+}
+
+/// Moves every body along its solved velocity.
+pub(crate) fn integrate_positions(bodies: &mut [SolverBody], dt: f32) {
+    for body in bodies.iter_mut() {
+        body.position += body.linear_velocity * dt;
+        body.rotation = Quat::from_rotation_z(body.angular_velocity * dt) * body.rotation;
+    }
+}
+
+/// Removes the remaining penetration of every contact, see [`ContactConstraint::correct_positions`].
+///
+/// The contacts between two bodies share their correction. A box resting on two manifold
+/// points, or a compound touching with several children, is moved apart once rather than
+/// once per point.
+pub(crate) fn correct_positions(bodies: &mut [SolverBody], contacts: &[ContactConstraint], solver_config: &SolverConfig) {
+    let mut contacts_per_pair: HashMap<(usize, usize), u32> = HashMap::default();
+    for contact in contacts.iter().filter(|c| c.current_depth(bodies) > solver_config.penetration_slop) {
+        *contacts_per_pair.entry((contact.body_1, contact.body_2)).or_default() += 1;
+    }
+    for contact in contacts {
+        let count = contacts_per_pair.get(&(contact.body_1, contact.body_2)).copied().unwrap_or(1);
+        contact.correct_positions(bodies, solver_config, 1. / count as f32);
+    }
+}
@@ -0,0 +1,278 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    broadphase::PhysicsBroadphase,
+    ccd,
+    collision_primitives::{self, CircleCollider, Primitives, RayIntersection},
+    gjk,
+    rigidbody::Rigidbody,
+};
+
+/// Gap at which a swept shape counts as touching.
+const CAST_TOLERANCE: f32 = 0.01;
+
+/// A ray hitting a collider, see [`SpatialQuery::cast_ray`].
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    pub entity: Entity,
+    /// World space point where the ray enters the collider.
+    pub point: Vec2,
+    /// Surface normal at `point`, facing the ray. Zero if the ray starts inside the collider.
+    pub normal: Vec2,
+    /// Distance along the ray in multiples of its direction.
+    pub toi: f32,
+}
+
+/// A swept shape hitting a collider, see [`SpatialQuery::cast_shape`].
+#[derive(Debug, Clone, Copy)]
+pub struct ShapeHit {
+    pub entity: Entity,
+    /// Distance the shape travelled in multiples of the direction it was cast in.
+    pub toi: f32,
+    /// Closest points on the swept shape at `toi` and on the collider.
+    pub point_1: Vec2,
+    pub point_2: Vec2,
+    /// Points from the collider towards the swept shape.
+    pub normal: Vec2,
+}
+
+/// The point on a collider's surface closest to a query point, see
+/// [`SpatialQuery::point_project`].
+#[derive(Debug, Clone, Copy)]
+pub struct PointProjection {
+    pub entity: Entity,
+    pub point: Vec2,
+    /// Whether the query point lies inside the collider.
+    pub is_inside: bool,
+}
+
+/// Asks the world about its colliders from any system, outside of the physics step.
+///
+/// Reads the colliders as of the last step. It borrows every [`Rigidbody`] immutably, so
+/// it cannot be used in a system that also queries them mutably.
+#[derive(SystemParam)]
+pub struct SpatialQuery<'w, 's> {
+    colliders: Query<'w, 's, (Entity, &'static Rigidbody, &'static Transform)>,
+    broadphase: Res<'w, PhysicsBroadphase>,
+}
+
+impl<'w, 's> SpatialQuery<'w, 's> {
+    /// First collider hit by the ray `origin + dir * t` with `t` between zero and `max_toi`.
+    ///
+    /// Only entities for which `filter` returns `true` are tested, e.g. to skip the body
+    /// the ray is cast from.
+    pub fn cast_ray(&self, origin: Vec2, dir: Vec2, max_toi: f32, filter: impl Fn(Entity) -> bool) -> Option<RayHit> {
+        let mut closest: Option<RayHit> = None;
+        for entity in self.ray_candidates(origin, dir, max_toi) {
+            // Nothing past the closest hit so far can be any closer
+            let max_toi = closest.map_or(max_toi, |hit| hit.toi);
+            if let Some(hit) = self.cast_ray_against(entity, origin, dir, max_toi, &filter) {
+                closest = Some(hit);
+            }
+        }
+        closest
+    }
+
+    /// Every collider hit by the ray, sorted from the closest to the furthest.
+    pub fn cast_ray_all(&self, origin: Vec2, dir: Vec2, max_toi: f32, filter: impl Fn(Entity) -> bool) -> Vec<RayHit> {
+        let mut hits: Vec<RayHit> = self
+            .ray_candidates(origin, dir, max_toi)
+            .into_iter()
+            .filter_map(|entity| self.cast_ray_against(entity, origin, dir, max_toi, &filter))
+            .collect();
+        hits.sort_by(|h_1, h_2| h_1.toi.total_cmp(&h_2.toi));
+        hits
+    }
+
+    /// First collider hit by `shape` moving from `transform` along `dir`, for up to
+    /// `max_toi` times `dir`. The shape does not rotate while it moves.
+    ///
+    /// A shape that already overlaps a collider hits it at `toi` zero, with the normal and
+    /// deepest points from the narrowphase the simulation uses.
+    pub fn cast_shape(
+        &self,
+        shape: &Primitives,
+        transform: &Transform,
+        dir: Vec2,
+        max_toi: f32,
+        filter: impl Fn(Entity) -> bool,
+    ) -> Option<ShapeHit> {
+        let motion = dir * max_toi;
+        let (min, max) = shape.get_world_bounds(transform);
+        let (min, max) = (min.min(min + motion), max.max(max + motion));
+        let mut closest: Option<ShapeHit> = None;
+        for entity in self.candidates(|node_min, node_max| node_min.cmple(max).all() && node_max.cmpge(min).all()) {
+            if !filter(entity) {
+                continue;
+            }
+            let Ok((_, rigidbody, other_transform)) = self.colliders.get(entity) else {
+                continue;
+            };
+            let Some(hit) = cast_shape_against(entity, shape, transform, motion, &rigidbody.shape, other_transform) else {
+                continue;
+            };
+            match closest {
+                Some(closest) if closest.toi <= hit.toi * max_toi => {}
+                _ => closest = Some(ShapeHit { toi: hit.toi * max_toi, ..hit }),
+            }
+        }
+        closest
+    }
+
+    /// Every entity whose collider contains `point`.
+    pub fn intersections_with_point(&self, point: Vec2, filter: impl Fn(Entity) -> bool) -> Vec<Entity> {
+        self.candidates(|min, max| point.cmpge(min).all() && point.cmple(max).all())
+            .into_iter()
+            .filter(|entity| filter(*entity))
+            .filter(|entity| match self.colliders.get(*entity) {
+                Ok((_, rigidbody, transform)) => rigidbody.shape.contains_point(transform, point),
+                Err(_) => false,
+            })
+            .collect()
+    }
+
+    /// Every entity whose collider overlaps `shape` placed at `transform`.
+    pub fn intersections_with_shape(&self, shape: &Primitives, transform: &Transform, filter: impl Fn(Entity) -> bool) -> Vec<Entity> {
+        let (min, max) = shape.get_world_bounds(transform);
+        self.candidates(|node_min, node_max| node_min.cmple(max).all() && node_max.cmpge(min).all())
+            .into_iter()
+            .filter(|entity| filter(*entity))
+            .filter(|entity| match self.colliders.get(*entity) {
+                Ok((_, rigidbody, other_transform)) => {
+                    !crate::collide_shapes(shape, transform, &rigidbody.shape, other_transform, 0.).is_empty()
+                }
+                Err(_) => false,
+            })
+            .collect()
+    }
+
+    /// Projects `point` onto the surface of the closest collider. A point inside a collider
+    /// is projected onto that collider's surface.
+    pub fn point_project(&self, point: Vec2, filter: impl Fn(Entity) -> bool) -> Option<PointProjection> {
+        let mut closest: Option<(f32, PointProjection)> = None;
+        for (entity, rigidbody, transform) in self.colliders.iter().filter(|(e, ..)| filter(*e)) {
+            let (min, max) = rigidbody.shape.get_world_bounds(transform);
+            // Cannot beat the closest so far if even its bounds are further away
+            let bounds_distance = (point.clamp(min, max) - point).length();
+            if matches!(closest, Some((distance, _)) if distance <= bounds_distance) {
+                continue;
+            }
+            let Some((distance, projected, is_inside)) = project_point(point, &rigidbody.shape, transform) else {
+                continue;
+            };
+            match closest {
+                Some((closest_distance, _)) if closest_distance <= distance => {}
+                _ => closest = Some((distance, PointProjection { entity, point: projected, is_inside })),
+            }
+        }
+        closest.map(|(_, projection)| projection)
+    }
+
+    fn cast_ray_against(
+        &self,
+        entity: Entity,
+        origin: Vec2,
+        dir: Vec2,
+        max_toi: f32,
+        filter: &impl Fn(Entity) -> bool,
+    ) -> Option<RayHit> {
+        if !filter(entity) {
+            return None;
+        }
+        let (_, rigidbody, transform) = self.colliders.get(entity).ok()?;
+        let RayIntersection { toi, normal } = rigidbody.shape.cast_ray(transform, origin, dir, max_toi)?;
+        Some(RayHit { entity, point: origin + dir * toi, normal, toi })
+    }
+
+    fn ray_candidates(&self, origin: Vec2, dir: Vec2, max_toi: f32) -> Vec<Entity> {
+        self.candidates(|min, max| collision_primitives::cast_ray_bounds(min, max, origin, dir, max_toi).is_some())
+    }
+
+    /// Entities with bounds for which `hits_bounds` returns `true`. Uses the broadphase
+    /// trees when there are any, colliders added since the last step are not in them yet
+    /// and always tested.
+    fn candidates(&self, hits_bounds: impl Fn(Vec2, Vec2) -> bool) -> Vec<Entity> {
+        let trees = self.broadphase.0.trees();
+        let mut candidates = Vec::new();
+        for tree in &trees {
+            tree.traverse(&hits_bounds, |entity| candidates.push(entity));
+        }
+        for (entity, rigidbody, transform) in self.colliders.iter() {
+            if trees.iter().any(|tree| tree.contains(entity)) {
+                continue;
+            }
+            let (min, max) = rigidbody.shape.get_world_bounds(transform);
+            if hits_bounds(min, max) {
+                candidates.push(entity);
+            }
+        }
+        candidates
+    }
+}
+
+/// [`SpatialQuery::cast_shape`] against a single collider, with `toi` as a fraction of `motion`.
+fn cast_shape_against(
+    entity: Entity,
+    shape: &Primitives,
+    transform: &Transform,
+    motion: Vec2,
+    other: &Primitives,
+    other_transform: &Transform,
+) -> Option<ShapeHit> {
+    let deepest = crate::collide_shapes(shape, transform, other, other_transform, 0.)
+        .into_iter()
+        .flat_map(|(_, _, manifold)| manifold.collision_data().map(|(_, c)| c).collect::<Vec<_>>())
+        .max_by(|c_1, c_2| c_1.penetration_depth.total_cmp(&c_2.penetration_depth));
+    if let Some(contact) = deepest {
+        let half_depth = contact.unit_normal * contact.penetration_depth / 2.;
+        return Some(ShapeHit {
+            entity,
+            toi: 0.,
+            point_1: contact.contact_point - half_depth,
+            point_2: contact.contact_point + half_depth,
+            normal: contact.unit_normal,
+        });
+    }
+    let (toi, closest) = ccd::shape_time_of_impact(shape, transform, motion, other, other_transform, CAST_TOLERANCE)?;
+    Some(ShapeHit {
+        entity,
+        toi,
+        point_1: closest.point_1,
+        point_2: closest.point_2,
+        normal: closest.normal,
+    })
+}
+
+/// Distance from `point` to the surface of `shape`, the closest point on that surface
+/// and whether `point` lies inside the shape.
+fn project_point(point: Vec2, shape: &Primitives, transform: &Transform) -> Option<(f32, Vec2, bool)> {
+    let probe = CircleCollider::new(0.);
+    let probe_transform = Transform::from_translation(point.extend(0.));
+    if shape.contains_point(transform, point) {
+        // The narrowphase pushes the probe out through the nearest surface. A point right on
+        // the surface does not overlap at all and is its own projection
+        let Some((_, _, manifold)) = crate::collide_shapes(&Primitives::Circle(probe), &probe_transform, shape, transform, 0.)
+            .into_iter()
+            .min_by(|(.., m_1), (.., m_2)| deepest(m_1).total_cmp(&deepest(m_2)))
+        else {
+            return Some((0., point, true));
+        };
+        let depth = deepest(&manifold);
+        return Some((depth, point + manifold.unit_normal * depth, true));
+    }
+    let mut closest: Option<(f32, Vec2)> = None;
+    ccd::for_each_convex_part(shape, transform, &mut |part, part_transform, _| {
+        let Some(points) = gjk::distance(&probe, &probe_transform, part, part_transform) else {
+            return;
+        };
+        match closest {
+            Some((distance, _)) if distance <= points.distance => {}
+            _ => closest = Some((points.distance, points.point_2)),
+        }
+    });
+    closest.map(|(distance, projected)| (distance, projected, false))
+}
+
+fn deepest(manifold: &collision_primitives::ContactManifold) -> f32 {
+    manifold.points.iter().map(|p| p.penetration_depth).fold(0., f32::max)
+}
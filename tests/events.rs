@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use bevy_2d_physics::{
+    collision_primitives::{CircleCollider, Primitives, AABB},
+    events::{CollisionEnded, CollisionStarted, Contact},
+    rigidbody::{Restitution, Rigidbody, Sensor},
+    Gravity, PhysicsAccumulator, PhysicsConfig, PhysicsPlugin,
+};
+
+#[derive(Default, Debug)]
+struct Counts {
+    started: usize,
+    ended: usize,
+    contacts: usize,
+}
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins).add_plugin(PhysicsPlugin);
+    app
+}
+
+/// Runs `n` fixed steps, adding up the events sent during each of them.
+fn step(app: &mut App, n: usize, counts: &mut Counts, mut on_contact: impl FnMut(&Contact)) {
+    for _ in 0..n {
+        let timestep = app.world.resource::<PhysicsConfig>().timestep;
+        app.world.resource_mut::<PhysicsAccumulator>().0 = timestep;
+        app.update();
+        counts.started += app.world.resource::<Events<CollisionStarted>>().iter_current_update_events().count();
+        counts.ended += app.world.resource::<Events<CollisionEnded>>().iter_current_update_events().count();
+        for contact in app.world.resource::<Events<Contact>>().iter_current_update_events() {
+            on_contact(contact);
+            counts.contacts += 1;
+        }
+    }
+}
+
+#[test]
+fn ball_dropped_on_floor() {
+    let mut app = app();
+    let floor = app.world.spawn((
+        Rigidbody::new_static_shape(Primitives::AABB(AABB::new(1000., 20.))),
+        Transform::from_xyz(0., -10., 0.),
+    )).id();
+    let mut rigidbody = Rigidbody::new_shape_with_velocity(1., Primitives::Circle(CircleCollider::new(10.)), Vec2::ZERO);
+    rigidbody.restitution = Restitution::new(0.);
+    let ball = app.world.spawn((rigidbody, Transform::from_xyz(0., 60., 0.))).id();
+
+    let mut counts = Counts::default();
+    let check = |contact: &Contact| {
+        let mut bodies = [contact.id.entity_1, contact.id.entity_2];
+        bodies.sort();
+        let mut expected = [ball, floor];
+        expected.sort();
+        assert_eq!(bodies, expected);
+        assert_eq!((contact.id.child_1, contact.id.child_2), (0, 0));
+        // Whichever way round, the floor pushes the ball up
+        let on_ball = if contact.id.entity_1 == ball { contact.impulse } else { -contact.impulse };
+        assert!(on_ball.y >= 0., "{contact:?}");
+    };
+
+    step(&mut app, 10, &mut counts, check);
+    assert_eq!((counts.started, counts.ended, counts.contacts), (0, 0, 0), "still falling");
+
+    step(&mut app, 50, &mut counts, check);
+    assert_eq!((counts.started, counts.ended), (1, 0), "{counts:?}");
+    let resting = counts.contacts;
+    assert!(resting >= 25, "a contact every step once landed, got {counts:?}");
+
+    app.world.get_mut::<Rigidbody>(ball).unwrap().linear_velocity = Vec2::new(0., 1000.);
+    step(&mut app, 10, &mut counts, check);
+    assert_eq!((counts.started, counts.ended), (1, 1), "{counts:?}");
+    assert!(counts.contacts <= resting + 1, "{counts:?}");
+}
+
+#[test]
+fn ball_through_sensor() {
+    let mut app = app();
+    app.insert_resource(Gravity(Vec2::ZERO));
+    app.world.spawn((
+        Rigidbody::new_static_shape(Primitives::AABB(AABB::new(100., 20.))),
+        Transform::default(),
+        Sensor,
+    ));
+    let velocity = Vec2::new(0., -300.);
+    let rigidbody = Rigidbody::new_shape_with_velocity(1., Primitives::Circle(CircleCollider::new(10.)), velocity);
+    let ball = app.world.spawn((rigidbody, Transform::from_xyz(0., 30., 0.))).id();
+
+    let mut counts = Counts::default();
+    step(&mut app, 5, &mut counts, |_| {});
+    assert_eq!((counts.started, counts.ended), (1, 0), "{counts:?}");
+    step(&mut app, 15, &mut counts, |_| {});
+    assert_eq!((counts.started, counts.ended, counts.contacts), (1, 1, 0), "{counts:?}");
+
+    // Went straight through at the same speed
+    assert_eq!(app.world.get::<Rigidbody>(ball).unwrap().linear_velocity, velocity);
+    let y = app.world.get::<Transform>(ball).unwrap().translation.y;
+    assert!((y - (30. - 20. * 5.)).abs() < 1e-3, "{y}");
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+use bevy_2d_physics::{
+    collision_primitives::{CircleCollider, Primitives, AABB},
+    rigidbody::{ContinuousCollision, Restitution, Rigidbody},
+    Gravity, PhysicsAccumulator, PhysicsConfig, PhysicsPlugin, SolverConfig,
+};
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins).add_plugin(PhysicsPlugin);
+    app.insert_resource(Gravity(Vec2::ZERO));
+    app
+}
+
+fn step(app: &mut App) {
+    let timestep = app.world.resource::<PhysicsConfig>().timestep;
+    app.world.resource_mut::<PhysicsAccumulator>().0 = timestep;
+    app.update();
+}
+
+/// A wall 2 thick with its left face at x 99, and a ball of radius 5 at the origin moving
+/// right 110 per step, so it is never caught overlapping the wall at the end of a step.
+fn thin_wall_and_fast_ball(app: &mut App) -> Entity {
+    app.world.spawn((
+        Rigidbody::new_static_shape(Primitives::AABB(AABB::new(2., 200.))),
+        Transform::from_xyz(100., 0., 0.),
+    ));
+    let speed = 110. / app.world.resource::<PhysicsConfig>().timestep;
+    let ball = Rigidbody::new_shape_with_velocity(1., Primitives::Circle(CircleCollider::new(5.)), Vec2::new(speed, 0.));
+    app.world.spawn((ball, Transform::default())).id()
+}
+
+fn x_of(app: &App, entity: Entity) -> f32 {
+    app.world.get::<Transform>(entity).unwrap().translation.x
+}
+
+#[test]
+fn fast_body_tunnels_without_ccd() {
+    let mut app = app();
+    let ball = thin_wall_and_fast_ball(&mut app);
+    step(&mut app);
+    step(&mut app);
+    assert!(x_of(&app, ball) > 200., "{}", x_of(&app, ball));
+}
+
+#[test]
+fn continuous_collision_stops_tunnelling() {
+    let mut app = app();
+    let ball = thin_wall_and_fast_ball(&mut app);
+    app.world.entity_mut(ball).insert(ContinuousCollision);
+    for _ in 0..60 {
+        step(&mut app);
+        // The ball's right edge never gets past the face of the wall
+        assert!(x_of(&app, ball) + 5. < 99.5, "{}", x_of(&app, ball));
+    }
+    // Bounced back off the wall
+    assert!(app.world.get::<Rigidbody>(ball).unwrap().linear_velocity.x < 0.);
+}
+
+#[test]
+fn speculative_contacts_stop_fast_body_at_wall() {
+    let mut app = app();
+    app.insert_resource(SolverConfig { speculative_contacts: true, ..Default::default() });
+    let ball = thin_wall_and_fast_ball(&mut app);
+    app.world.get_mut::<Rigidbody>(ball).unwrap().restitution = Restitution::new(0.);
+    for _ in 0..60 {
+        step(&mut app);
+        assert!(x_of(&app, ball) + 5. < 99.5, "{}", x_of(&app, ball));
+    }
+    // Came to rest against the face of the wall
+    assert!((x_of(&app, ball) - 94.).abs() < 0.5, "{}", x_of(&app, ball));
+    let velocity = app.world.get::<Rigidbody>(ball).unwrap().linear_velocity;
+    assert!(velocity.length() < 1., "{velocity}");
+}
+
+#[test]
+fn speculative_contacts_stop_body_already_touching_wall() {
+    let mut app = app();
+    app.insert_resource(SolverConfig { speculative_contacts: true, ..Default::default() });
+    let ball = thin_wall_and_fast_ball(&mut app);
+    app.world.get_mut::<Rigidbody>(ball).unwrap().restitution = Restitution::new(0.);
+    // Exactly touching is neither overlapping nor apart
+    app.world.get_mut::<Transform>(ball).unwrap().translation.x = 94.;
+    step(&mut app);
+    assert!((x_of(&app, ball) - 94.).abs() < 0.5, "{}", x_of(&app, ball));
+}
+
+#[test]
+fn speculative_contacts_bounce_fast_body_off_wall() {
+    let mut app = app();
+    app.insert_resource(SolverConfig { speculative_contacts: true, ..Default::default() });
+    let ball = thin_wall_and_fast_ball(&mut app);
+    let speed = app.world.get::<Rigidbody>(ball).unwrap().linear_velocity.x;
+    for _ in 0..10 {
+        step(&mut app);
+        assert!(x_of(&app, ball) + 5. < 99.5, "{}", x_of(&app, ball));
+    }
+    // Bounced with the speed it hit the wall at, not the speed it was slowed to
+    let velocity = app.world.get::<Rigidbody>(ball).unwrap().linear_velocity;
+    assert!((velocity.x + 0.8 * speed).abs() < 1., "{velocity}");
+}
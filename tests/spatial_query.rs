@@ -0,0 +1,234 @@
+use bevy::{ecs::system::SystemState, prelude::*};
+use bevy_2d_physics::{
+    broadphase::{DynamicAabbTree, PhysicsBroadphase},
+    collision_primitives::{CircleCollider, ConvexPolygon, Primitives, AABB},
+    rigidbody::Rigidbody,
+    spatial_query::SpatialQuery,
+    PhysicsAccumulator, PhysicsConfig, PhysicsPlugin,
+};
+
+fn spawn(world: &mut World, shape: Primitives, x: f32, y: f32) -> Entity {
+    world.spawn((Rigidbody::new_static_shape(shape), Transform::from_xyz(x, y, 0.))).id()
+}
+
+/// Runs `f` with a [`SpatialQuery`] over the bodies spawned into `world`.
+fn query<R>(world: &mut World, f: impl FnOnce(&SpatialQuery) -> R) -> R {
+    world.init_resource::<PhysicsBroadphase>();
+    let mut state: SystemState<SpatialQuery> = SystemState::new(world);
+    f(&state.get(world))
+}
+
+fn assert_near(a: Vec2, b: Vec2) {
+    assert!(a.distance(b) < 1e-3, "{a} is not {b}");
+}
+
+#[test]
+fn point_project_circle() {
+    let mut world = World::new();
+    let circle = spawn(&mut world, Primitives::Circle(CircleCollider::new(5.)), 10., 10.);
+    query(&mut world, |q| {
+        // Right on the centre every direction is as good as any other
+        let p = q.point_project(Vec2::new(10., 10.), |_| true).unwrap();
+        assert!(p.entity == circle && p.is_inside, "{p:?}");
+        assert!((p.point.distance(Vec2::new(10., 10.)) - 5.).abs() < 1e-3, "{p:?}");
+
+        let p = q.point_project(Vec2::new(15., 10.), |_| true).unwrap();
+        assert!(p.entity == circle && p.is_inside, "{p:?}");
+        assert_near(p.point, Vec2::new(15., 10.));
+
+        let p = q.point_project(Vec2::new(12., 10.), |_| true).unwrap();
+        assert!(p.is_inside, "{p:?}");
+        assert_near(p.point, Vec2::new(15., 10.));
+
+        let p = q.point_project(Vec2::new(10., 30.), |_| true).unwrap();
+        assert!(!p.is_inside, "{p:?}");
+        assert_near(p.point, Vec2::new(10., 15.));
+    });
+}
+
+#[test]
+fn point_project_box_and_polygon() {
+    let mut world = World::new();
+    let aabb = spawn(&mut world, Primitives::AABB(AABB::new(20., 40.)), 200., 0.);
+    let triangle = ConvexPolygon::new(vec![Vec2::new(-30., -30.), Vec2::new(30., -30.), Vec2::new(0., 60.)]).unwrap();
+    let polygon = spawn(&mut world, Primitives::ConvexPolygon(triangle), 0., 200.);
+    query(&mut world, |q| {
+        let p = q.point_project(Vec2::new(150., 3.), |_| true).unwrap();
+        assert!(p.entity == aabb && !p.is_inside, "{p:?}");
+        assert_near(p.point, Vec2::new(190., 3.));
+
+        let p = q.point_project(Vec2::new(207., 1.), |_| true).unwrap();
+        assert!(p.entity == aabb && p.is_inside, "{p:?}");
+        assert_near(p.point, Vec2::new(210., 1.));
+
+        let p = q.point_project(Vec2::new(210., 5.), |_| true).unwrap();
+        assert!(p.entity == aabb && p.is_inside, "{p:?}");
+        assert_near(p.point, Vec2::new(210., 5.));
+
+        // Centred on its centroid, so the bottom edge is 30 below the transform
+        let p = q.point_project(Vec2::new(0., 175.), |_| true).unwrap();
+        assert!(p.entity == polygon && p.is_inside, "{p:?}");
+        assert_near(p.point, Vec2::new(0., 170.));
+
+        let p = q.point_project(Vec2::new(150., 3.), |e| e != aabb).unwrap();
+        assert_eq!(p.entity, polygon);
+    });
+}
+
+#[test]
+fn intersections_with_point() {
+    let mut world = World::new();
+    let circle = spawn(&mut world, Primitives::Circle(CircleCollider::new(10.)), 100., 0.);
+    let aabb = spawn(&mut world, Primitives::AABB(AABB::new(20., 40.)), 105., 0.);
+    query(&mut world, |q| {
+        let mut both = q.intersections_with_point(Vec2::new(100., 0.), |_| true);
+        both.sort();
+        assert_eq!(both, vec![circle, aabb]);
+        assert_eq!(q.intersections_with_point(Vec2::new(110., 15.), |_| true), vec![aabb]);
+        assert_eq!(q.intersections_with_point(Vec2::new(92., 0.), |_| true), vec![circle]);
+        assert!(q.intersections_with_point(Vec2::new(110., 15.), |e| e != aabb).is_empty());
+        assert!(q.intersections_with_point(Vec2::new(0., 0.), |_| true).is_empty());
+    });
+}
+
+#[test]
+fn intersections_with_shape() {
+    let mut world = World::new();
+    let circle = spawn(&mut world, Primitives::Circle(CircleCollider::new(10.)), 0., 0.);
+    let aabb = spawn(&mut world, Primitives::AABB(AABB::new(20., 20.)), 50., 0.);
+    let probe = Primitives::Circle(CircleCollider::new(5.));
+    query(&mut world, |q| {
+        assert_eq!(q.intersections_with_shape(&probe, &Transform::from_xyz(0., 12., 0.), |_| true), vec![circle]);
+        assert_eq!(q.intersections_with_shape(&probe, &Transform::from_xyz(37., 0., 0.), |_| true), vec![aabb]);
+        let mut both = q.intersections_with_shape(&Primitives::AABB(AABB::new(60., 4.)), &Transform::from_xyz(25., 0., 0.), |_| true);
+        both.sort();
+        assert_eq!(both, vec![circle, aabb]);
+        // Bounds overlap at the corner but the shapes do not
+        assert!(q.intersections_with_shape(&probe, &Transform::from_xyz(11., 11., 0.), |_| true).is_empty());
+        assert!(q.intersections_with_shape(&probe, &Transform::from_xyz(0., 12., 0.), |e| e != circle).is_empty());
+    });
+}
+
+#[test]
+fn cast_ray() {
+    let mut world = World::new();
+    let circle = spawn(&mut world, Primitives::Circle(CircleCollider::new(10.)), 100., 0.);
+    let aabb = spawn(&mut world, Primitives::AABB(AABB::new(20., 20.)), 200., 0.);
+    query(&mut world, |q| {
+        let hit = q.cast_ray(Vec2::ZERO, Vec2::X, 1000., |_| true).unwrap();
+        assert_eq!(hit.entity, circle);
+        assert!((hit.toi - 90.).abs() < 1e-3, "{hit:?}");
+        assert_near(hit.point, Vec2::new(90., 0.));
+        assert_near(hit.normal, Vec2::NEG_X);
+
+        // Time of impact in multiples of the direction
+        let hit = q.cast_ray(Vec2::ZERO, Vec2::new(2., 0.), 1000., |_| true).unwrap();
+        assert!((hit.toi - 45.).abs() < 1e-3, "{hit:?}");
+        assert_near(hit.point, Vec2::new(90., 0.));
+
+        let hit = q.cast_ray(Vec2::ZERO, Vec2::X, 1000., |e| e != circle).unwrap();
+        assert_eq!(hit.entity, aabb);
+        assert!((hit.toi - 190.).abs() < 1e-3, "{hit:?}");
+
+        // Starting inside
+        let hit = q.cast_ray(Vec2::new(95., 0.), Vec2::X, 1000., |_| true).unwrap();
+        assert!(hit.entity == circle && hit.toi == 0. && hit.normal == Vec2::ZERO, "{hit:?}");
+
+        assert!(q.cast_ray(Vec2::ZERO, Vec2::X, 80., |_| true).is_none());
+        assert!(q.cast_ray(Vec2::ZERO, Vec2::Y, 1000., |_| true).is_none());
+        assert!(q.cast_ray(Vec2::ZERO, Vec2::NEG_X, 1000., |_| true).is_none());
+    });
+}
+
+#[test]
+fn cast_ray_all() {
+    let mut world = World::new();
+    let far = spawn(&mut world, Primitives::AABB(AABB::new(20., 20.)), 200., 0.);
+    let near = spawn(&mut world, Primitives::Circle(CircleCollider::new(10.)), 100., 0.);
+    let off_ray = spawn(&mut world, Primitives::Circle(CircleCollider::new(10.)), 150., 50.);
+    query(&mut world, |q| {
+        let hits = q.cast_ray_all(Vec2::ZERO, Vec2::X, 1000., |_| true);
+        assert_eq!(hits.iter().map(|h| h.entity).collect::<Vec<_>>(), vec![near, far]);
+        assert!((hits[0].toi - 90.).abs() < 1e-3 && (hits[1].toi - 190.).abs() < 1e-3, "{hits:?}");
+
+        let hits = q.cast_ray_all(Vec2::ZERO, Vec2::X, 150., |_| true);
+        assert_eq!(hits.iter().map(|h| h.entity).collect::<Vec<_>>(), vec![near]);
+
+        let hits = q.cast_ray_all(Vec2::new(95., 0.), Vec2::X, 1000., |e| e != off_ray);
+        assert_eq!(hits.iter().map(|h| h.entity).collect::<Vec<_>>(), vec![near, far]);
+        assert_eq!(hits[0].toi, 0.);
+
+        let hits = q.cast_ray_all(Vec2::new(150., 0.), Vec2::Y, 1000., |_| true);
+        assert_eq!(hits.iter().map(|h| h.entity).collect::<Vec<_>>(), vec![off_ray]);
+        assert!(q.cast_ray_all(Vec2::new(150., 0.), Vec2::Y, 1000., |e| e != off_ray).is_empty());
+    });
+}
+
+#[test]
+fn cast_ray_through_broadphase_tree() {
+    let mut app = App::new();
+    app.insert_resource(PhysicsBroadphase(Box::<DynamicAabbTree>::default()))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(PhysicsPlugin);
+    let far = spawn(&mut app.world, Primitives::AABB(AABB::new(20., 20.)), 200., 0.);
+    let timestep = app.world.resource::<PhysicsConfig>().timestep;
+    app.world.resource_mut::<PhysicsAccumulator>().0 = timestep;
+    app.update();
+    // Not in the broadphase until the next step
+    let near = spawn(&mut app.world, Primitives::Circle(CircleCollider::new(10.)), 100., 0.);
+
+    let mut state: SystemState<SpatialQuery> = SystemState::new(&mut app.world);
+    let q = state.get(&app.world);
+    let hits = q.cast_ray_all(Vec2::ZERO, Vec2::X, 1000., |_| true);
+    assert_eq!(hits.iter().map(|h| h.entity).collect::<Vec<_>>(), vec![near, far]);
+    assert_eq!(q.cast_ray(Vec2::new(150., 0.), Vec2::X, 1000., |_| true).unwrap().entity, far);
+}
+
+#[test]
+fn cast_shape_initial_overlap() {
+    let mut world = World::new();
+    let aabb = spawn(&mut world, Primitives::AABB(AABB::new(20., 20.)), 0., 0.);
+    let probe = Primitives::Circle(CircleCollider::new(5.));
+    query(&mut world, |q| {
+        // Sunk 2 into the top face, whichever way it is then cast
+        let hit = q.cast_shape(&probe, &Transform::from_xyz(0., 13., 0.), Vec2::X, 100., |_| true).unwrap();
+        assert_eq!(hit.entity, aabb);
+        assert_eq!(hit.toi, 0.);
+        assert_near(hit.normal, Vec2::Y);
+        assert_near(hit.point_1, Vec2::new(0., 8.));
+        assert_near(hit.point_2, Vec2::new(0., 10.));
+
+        assert!(q.cast_shape(&probe, &Transform::from_xyz(0., 13., 0.), Vec2::X, 100., |e| e != aabb).is_none());
+    });
+}
+
+#[test]
+fn cast_shape_sweep() {
+    let mut world = World::new();
+    let near = spawn(&mut world, Primitives::AABB(AABB::new(20., 20.)), 100., 0.);
+    let far = spawn(&mut world, Primitives::Circle(CircleCollider::new(10.)), 200., 0.);
+    let probe = Primitives::Circle(CircleCollider::new(5.));
+    let start = Transform::default();
+    query(&mut world, |q| {
+        // Touches the left face at x 90 once its centre reaches 85
+        let hit = q.cast_shape(&probe, &start, Vec2::X, 1000., |_| true).unwrap();
+        assert_eq!(hit.entity, near);
+        assert!((hit.toi - 85.).abs() < 0.05, "{hit:?}");
+        assert_near(hit.normal, Vec2::NEG_X);
+        assert!(hit.point_2.distance(Vec2::new(90., 0.)) < 0.05, "{hit:?}");
+        assert!(hit.point_1.distance(Vec2::new(90., 0.)) < 0.05, "{hit:?}");
+
+        // Time of impact and range in multiples of the direction
+        let hit = q.cast_shape(&probe, &start, Vec2::new(4., 0.), 1000., |_| true).unwrap();
+        assert!((hit.toi - 21.25).abs() < 0.05, "{hit:?}");
+        assert!(q.cast_shape(&probe, &start, Vec2::new(4., 0.), 20., |_| true).is_none());
+
+        let hit = q.cast_shape(&probe, &start, Vec2::X, 1000., |e| e != near).unwrap();
+        assert_eq!(hit.entity, far);
+        assert!((hit.toi - 185.).abs() < 0.05, "{hit:?}");
+
+        // Passes just above the box
+        assert!(q.cast_shape(&probe, &Transform::from_xyz(0., 16., 0.), Vec2::X, 1000., |_| true).is_none());
+        assert!(q.cast_shape(&probe, &start, Vec2::NEG_X, 1000., |_| true).is_none());
+    });
+}